@@ -1,19 +1,14 @@
-/*
-//#[cfg(target_os = "linux")]
+// Tạo schema/cw-starter.json (instantiate, execute, query kèm response, migrate)
+// cho codegen TypeScript: cargo schema
 use cosmwasm_schema::write_api;
 
+use cw_starter::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 
-//#[cfg(target_os = "linux")]
-use cw_starter::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-
-
-//#[cfg(target_os = "linux")]
 fn main() {
     write_api! {
         instantiate: InstantiateMsg,
         execute: ExecuteMsg,
         query: QueryMsg,
+        migrate: MigrateMsg,
     }
 }
-*/
-fn main() {}
@@ -4,13 +4,20 @@
 // =>  Mục đích: Chỉ import entry_point khi contract đang chạy độc lập, không phải là một thư viện.
 // #[cfg(...)] là attribute kiểm soát biên dịch (conditional compilation) trong Rust.
 // not(feature = "library") nghĩa là chỉ chạy đoạn code này nếu không bật feature "library".
-use cosmwasm_std::{entry_point, Addr};
+use cosmwasm_std::entry_point;
 // Định nghĩa điểm vào
 // entry_point là macro giúp xác định các hàm chính của contract khi deploy
 
 use cosmwasm_std::{
-    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult,
+    coin, to_json_binary, Addr, BankMsg, Binary, BlockInfo, CanonicalAddr, Coin, Decimal, Deps,
+    DepsMut, Env, MessageInfo, Order, Response, StdError, StdResult, Storage, Uint128,
 };
+use cw_storage_plus::{Bound, KeyDeserialize, Map, Prefixer, PrimaryKey};
+use ripemd::Ripemd160;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
 // các struct / enum của cosmwasm_std
 // ✅ Binary → Dữ liệu nhị phân (thường dùng cho query). là wrapper của Vec<u8>
 // ✅ Deps & DepsMut → Truy cập storage & dependencies. Đọc ghi dữ liệu vào storage
@@ -26,7 +33,7 @@ use cosmwasm_std::{
 //     pub querier: &'a dyn Querier,  // Truy vấn dữ liệu từ blockchain
 // }
 
-use cw2::set_contract_version;
+use cw2::{get_contract_version, set_contract_version};
 //Hàm hỗ trợ lưu trữ contract version
 // => dễ dàng theo dõi và nâng cấp
 //set_contract_version(storage, contract_name, contract_version)
@@ -42,15 +49,52 @@ use crate::error::ContractError;
 //crate -> tham chiếu đến gốc của project
 // error tự định nghĩa
 
+use crate::helpers::{compute_commitment, signed_vote_sign_doc};
 use crate::msg::{
-    AllPollsResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, PollResponse, QueryMsg, VoteResponse,
+    AllPollsResponse, CommitmentsResponse, DelegationResponse, DepositResponse, DepositsResponse,
+    ElectorateResponse, ExecuteMsg, InstantiateMsg, MarketParams, MarketResponse, MigrateMsg,
+    NonceResponse, OptionResult, PayoutResponse, PollHistoryResponse, PollPhase, PollResponse,
+    PollResultResponse, PollsResponse, QueryMsg, ReportsResponse, ResultsAtResponse,
+    ResultsResponse, RewardResponse, RoleMembersResponse, RolesResponse, SeriesHistoryResponse,
+    SeriesResponse, SignedVote, TallyResponse, TemplateResponse, TemplatesResponse,
+    UserOverviewResponse, VoteOption, VoteResponse, VotedPoll,
 };
 
-use crate::state::{Ballot, Config, Poll, BALLOTS, CONFIG, POLLS};
+use crate::state::{
+    Ballot, Commitment, Config, Deposit, Eligibility, Market, MarketStatus, OptionInfo,
+    OptionMetadata, PauseScope, PauseState, Poll, PollMetadata, PollResult, PollRevision,
+    PollStatus, RewardPool, RewardSplit, Role, Series, SeriesInstance, Stats, Template,
+    TemplateVotingMode, VoteChangePolicy, VotingMode, BALLOTS, COMMITMENTS, CONFIG, CREATED_POLLS,
+    DELEGATIONS, DELEGATORS, DEPOSITS, ELECTORATE, GLOBAL_SCOPE, LEGACY_BALLOTS, LEGACY_CONFIG,
+    LEGACY_POLLS, MARKETS, NONCES, OPTIONS, PAYOUT_CLAIMED, POLLS, POLL_DELEGATIONS, POLL_RESULTS,
+    POLL_REVISIONS, POLL_VOTERS, QUESTION_INDEX, REPORTS, REWARD_CLAIMED, REWARD_POOLS, ROLES,
+    SERIES, SERIES_HISTORY, STAKES, STATS, TAG_INDEX, TALLIES, TEMPLATES, TOP_POLLS_LEN, TURNOUT,
+    VOTERS, VOTER_WEIGHTS,
+};
 
 const CONTRACT_NAME: &str = "crates.io:cw-starter";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// phân trang cho các query danh sách
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+const MAX_REPORT_REASON_LEN: usize = 256;
+
+// số record tối đa bị xoá trong một lần PruneBallots
+const DEFAULT_PRUNE_LIMIT: u32 = 50;
+const MAX_PRUNE_LIMIT: u32 = 200;
+// số voter chốt weight mỗi lần (finalize chốt lô đầu, phần còn lại qua SettleRewards)
+const DEFAULT_SETTLE_LIMIT: u32 = 50;
+const MAX_SETTLE_LIMIT: u32 = 200;
+
+// giới hạn độ dài metadata của poll
+const MAX_DESCRIPTION_LEN: usize = 1024;
+const MAX_URI_LEN: usize = 256;
+const MAX_CATEGORY_LEN: usize = 64;
+const MAX_TAG_LEN: usize = 32;
+const MAX_TAGS: usize = 10;
+
 #[cfg_attr(not(feature = "library"), entry_point)] // là entry_point nếu file được chạy không như một lib
                                                    // => xác định gốc file, file contract chính sẽ thực thi
                                                    // Dòng #[cfg_attr(not(feature = "library"), entry_point)] có tác dụng như sau:
@@ -80,15 +124,27 @@ pub fn instantiate(
     //_infor.sender -> địa chỉ người gọi contract có kiểu Addr
     // Nếu giải _msg.admin ra None thì lấy admin là người deploy
 
-    ///eprintln!("{}", admin);
+    //eprintln!("{}", admin);
     let validated_admin = Addr::unchecked(admin.clone());
-    ///let validated_admin = _deps.api.addr_validate(&admin)?;
+    //let validated_admin = _deps.api.addr_validate(&admin)?;
     // Kiểm tra và xác định địa chỉ admin
     // gọi hàm addr_validate(&admin) để kiểm tra admin có hợp lệ không
     // nếu hợp lệ trả về Addr
     // Nếu không hợp lệ, trả về lỗi StdError::GenericErr { msg: "invalid address".to_string() }.
+    let community_pool = match _msg.community_pool {
+        Some(addr) => _deps.api.addr_validate(&addr)?,
+        None => validated_admin.clone(),
+    };
     let config = Config {
         admin: validated_admin.clone(),
+        // amount = 0 coi như không cần đặt cọc
+        poll_deposit: _msg.poll_deposit.filter(|coin| !coin.amount.is_zero()),
+        community_pool,
+        market_fee: Decimal::zero(),
+        paused: None,
+        restricted_creation: false,
+        report_threshold: None,
+        categories: vec![],
     };
 
     CONFIG.save(_deps.storage, &config)?;
@@ -110,494 +166,6963 @@ pub fn execute(
     _info: MessageInfo,
     _msg: ExecuteMsg, // chứa lệnh cần thực thi
 ) -> Result<Response, ContractError> {
+    check_not_paused(_deps.storage, &_env, &_msg)?;
+
     match _msg {
         ExecuteMsg::CreatePoll {
             poll_id,
             question,
             options,
-        } => execute_create_poll(_deps, _env, _info, poll_id, question, options),
+            voting_mode,
+            reward_split,
+            market,
+            eligibility,
+            metadata,
+            option_metadata,
+            vote_change,
+            duration,
+        } => execute_create_poll(
+            _deps,
+            _env,
+            _info,
+            poll_id,
+            question,
+            options,
+            voting_mode,
+            reward_split,
+            market,
+            eligibility,
+            metadata,
+            option_metadata,
+            vote_change,
+            duration,
+        ),
 
+        ExecuteMsg::CreatePollFromTemplate {
+            template_id,
+            poll_id,
+            params,
+        } => execute_create_poll_from_template(_deps, _env, _info, template_id, poll_id, params),
+        ExecuteMsg::SaveTemplate {
+            template_id,
+            template,
+        } => execute_save_template(_deps, _env, _info, template_id, Some(template)),
+        ExecuteMsg::RemoveTemplate { template_id } => {
+            execute_save_template(_deps, _env, _info, template_id, None)
+        }
+        ExecuteMsg::CreateSeries {
+            series_id,
+            template_id,
+            params,
+            period,
+            bounty,
+        } => execute_create_series(
+            _deps,
+            _env,
+            _info,
+            series_id,
+            template_id,
+            params,
+            period,
+            bounty,
+        ),
+        ExecuteMsg::AdvanceSeries { series_id } => {
+            execute_advance_series(_deps, _env, _info, series_id)
+        }
+        ExecuteMsg::FundSeries { series_id } => execute_fund_series(_deps, _env, _info, series_id),
+        ExecuteMsg::StopSeries { series_id } => execute_stop_series(_deps, _env, _info, series_id),
+        ExecuteMsg::UpdatePoll {
+            poll_id,
+            question,
+            options,
+            metadata,
+            option_metadata,
+        } => execute_update_poll(
+            _deps,
+            _env,
+            _info,
+            poll_id,
+            question,
+            options,
+            metadata,
+            option_metadata,
+        ),
         ExecuteMsg::Vote { poll_id, vote } => execute_vote(_deps, _env, _info, poll_id, vote),
+        ExecuteMsg::CommitVote {
+            poll_id,
+            commitment,
+        } => execute_commit_vote(_deps, _env, _info, poll_id, commitment),
+        ExecuteMsg::RevealVote {
+            poll_id,
+            vote,
+            salt,
+        } => execute_reveal_vote(_deps, _env, _info, poll_id, vote, salt),
+        ExecuteMsg::RetractVote { poll_id } => execute_retract_vote(_deps, _env, _info, poll_id),
+        ExecuteMsg::Delegate { to, poll_id } => execute_delegate(_deps, _env, _info, to, poll_id),
+        ExecuteMsg::Undelegate { poll_id } => execute_undelegate(_deps, _env, _info, poll_id),
+        ExecuteMsg::SubmitSignedVotes { votes } => {
+            execute_submit_signed_votes(_deps, _env, _info, votes)
+        }
+        ExecuteMsg::FinalizePoll { poll_id } => execute_finalize_poll(_deps, _env, _info, poll_id),
+        ExecuteMsg::CancelPoll { poll_id } => execute_cancel_poll(_deps, _env, _info, poll_id),
+        ExecuteMsg::HidePoll { poll_id } => execute_set_hidden(_deps, _env, _info, poll_id, true),
+        ExecuteMsg::UnhidePoll { poll_id } => {
+            execute_set_hidden(_deps, _env, _info, poll_id, false)
+        }
+        ExecuteMsg::ReportPoll { poll_id, reason } => {
+            execute_report_poll(_deps, _env, _info, poll_id, reason)
+        }
+        ExecuteMsg::DeletePoll { poll_id } => execute_delete_poll(_deps, _env, _info, poll_id),
+        ExecuteMsg::PruneBallots { poll_id, limit } => {
+            execute_prune_ballots(_deps, _env, _info, poll_id, limit)
+        }
+        ExecuteMsg::SettleRewards { poll_id, limit } => {
+            execute_settle_rewards(_deps, _env, _info, poll_id, limit)
+        }
+        ExecuteMsg::ClaimReward { poll_id } => execute_claim_reward(_deps, _env, _info, poll_id),
+        ExecuteMsg::ResolvePoll { poll_id, outcome } => {
+            execute_resolve_poll(_deps, _env, _info, poll_id, outcome)
+        }
+        ExecuteMsg::VoidPoll { poll_id } => execute_void_poll(_deps, _env, _info, poll_id),
+        ExecuteMsg::ClaimPayout { poll_id } => execute_claim_payout(_deps, _env, _info, poll_id),
+        ExecuteMsg::UpdateConfig {
+            admin,
+            poll_deposit,
+            community_pool,
+            market_fee,
+            restricted_creation,
+            report_threshold,
+            categories,
+        } => execute_update_config(
+            _deps,
+            _env,
+            _info,
+            admin,
+            poll_deposit,
+            community_pool,
+            market_fee,
+            restricted_creation,
+            report_threshold,
+            categories,
+        ),
+        ExecuteMsg::Pause {
+            scopes,
+            until_height,
+        } => execute_pause(_deps, _env, _info, scopes, until_height),
+        ExecuteMsg::Unpause {} => execute_unpause(_deps, _env, _info),
+        ExecuteMsg::GrantRole { address, role } => {
+            execute_grant_role(_deps, _env, _info, address, role)
+        }
+        ExecuteMsg::RevokeRole { address, role } => {
+            execute_revoke_role(_deps, _env, _info, address, role)
+        }
+        ExecuteMsg::UpdateElectorate { add, remove } => {
+            execute_update_electorate(_deps, _env, _info, add, remove)
+        }
     }
     //unimplemented!()
 }
 
+// Chặn các thao tác thuộc scope đang bị pause, thao tác của admin và query không bị ảnh hưởng
+fn check_not_paused(
+    storage: &dyn Storage,
+    env: &Env,
+    msg: &ExecuteMsg,
+) -> Result<(), ContractError> {
+    let scope = match msg {
+        ExecuteMsg::CreatePoll { .. }
+        | ExecuteMsg::CreatePollFromTemplate { .. }
+        | ExecuteMsg::AdvanceSeries { .. } => PauseScope::PollCreation,
+        ExecuteMsg::Vote { .. }
+        | ExecuteMsg::CommitVote { .. }
+        | ExecuteMsg::RevealVote { .. }
+        | ExecuteMsg::RetractVote { .. }
+        | ExecuteMsg::SubmitSignedVotes { .. }
+        | ExecuteMsg::Delegate { .. }
+        | ExecuteMsg::Undelegate { .. } => PauseScope::Voting,
+        ExecuteMsg::ClaimReward { .. } | ExecuteMsg::ClaimPayout { .. } => PauseScope::Claims,
+        _ => return Ok(()),
+    };
+
+    if let Some(paused) = CONFIG.load(storage)?.paused {
+        let expired = matches!(paused.until_height, Some(height) if env.block.height >= height);
+        if !expired && paused.scopes.contains(&scope) {
+            return Err(ContractError::Paused {});
+        }
+    }
+    Ok(())
+}
+
 fn execute_vote(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
     poll_id: String,
-    vote: String,
+    vote: VoteOption,
 ) -> Result<Response, ContractError> {
-    let poll = POLLS.may_load(deps.storage, poll_id.clone())?;
-
-    match poll {
-        Some(mut poll) => {
-            // The poll exists
-            BALLOTS.update(
-                // update BALLOTS tại key (info.sender, poll_id)
-                deps.storage,
-                (info.sender, poll_id.clone()),
-                |ballot| -> StdResult<Ballot> {
-                    //inline function thực hiện gì tại key (info.sender,id)
-                    //Nếu trả về Ok(value) thì update giá trị tại khóa thành value
-                    // tham số thứ 3 là closure  nhận vào 1 para là Option<T>, với T là kiểu của giá trị của Map
-                    // function F: FnOnce(Option<T>) -> StdResult<T>
-                    match ballot {
-                        Some(ballot) => {
-                            // trường hợp đã vote rồi giờ muốn vote lại
-                            // We need to revoke their old vote
-                            // Find the position
-                            let position_of_old_vote = poll
-                                .options
-                                .iter()
-                                .position(|option| option.0 == ballot.option)
-                                .unwrap(); // tìm vote trong poll.options mà tương đồng với cái vote đã từng chọn
-                                           // Decrement by 1
-                            poll.options[position_of_old_vote].1 -= 1;
-                            // Update the ballot
-                            Ok(Ballot {
-                                option: vote.clone(),
-                            }) //update vote mới
-                        }
-                        None => {
-                            //chưa vote trươc đó
-                            // Simply add the ballot
-                            Ok(Ballot {
-                                option: vote.clone(),
-                            }) //update vote mới
-                        }
-                    }
-                },
-            )?;
+    let poll = load_poll(deps.storage, &poll_id)?;
+    if poll.voting_mode != VotingMode::Public {
+        // poll commit-reveal phải đi qua CommitVote / RevealVote
+        return Err(ContractError::WrongVotingMode {});
+    }
+    check_not_ended(&poll, &_env)?;
+    let option = match vote {
+        VoteOption::Index(index) => index,
+        VoteOption::Label(label) => option_index(deps.storage, &poll_id, &label)?,
+    };
+    match MARKETS.may_load(deps.storage, poll_id.clone())? {
+        Some(market) => stake_vote(
+            deps.storage,
+            _env.block.height,
+            &poll_id,
+            market,
+            &info,
+            option,
+        )?,
+        // poll thường không nhận tiền gửi kèm
+        None if !info.funds.is_empty() => return Err(ContractError::UnexpectedFunds {}),
+        None => {}
+    }
+    tally_vote(
+        deps.storage,
+        &_env.block,
+        poll_id,
+        &poll,
+        info.sender,
+        option,
+    )?;
+    Ok(Response::new())
+}
 
-            // Find the position of the new vote option and increment it by 1
-            let position = poll //Tìm vote mới
-                .options
-                .iter()
-                .position(|option| option.0 == vote); // .position : tìm phần tử đầu tiên thỏa mãn điều kiện
-            if position.is_none() {
-                return Err(ContractError::Unauthorized {});
-            }
-            let position = position.unwrap();
-            poll.options[position].1 += 1;
+// Poll không tồn tại trả về PollNotFound thay vì lỗi NotFound chung của storage
+fn load_poll(storage: &dyn Storage, poll_id: &str) -> Result<Poll, ContractError> {
+    POLLS
+        .may_load(storage, poll_id.to_string())?
+        .ok_or_else(|| ContractError::PollNotFound {
+            poll_id: poll_id.to_string(),
+        })
+}
 
-            // Save the update
-            POLLS.save(deps.storage, poll_id, &poll)?;
-            Ok(Response::new())
+// Vote trong prediction market phải kèm stake, chỉ được stake thêm vào option đã chọn
+fn stake_vote(
+    storage: &mut dyn Storage,
+    height: u64,
+    poll_id: &str,
+    mut market: Market,
+    info: &MessageInfo,
+    option: u32,
+) -> Result<(), ContractError> {
+    if market.status != MarketStatus::Open || height >= market.close_height {
+        return Err(ContractError::PollNotOpen {});
+    }
+    let amount = match info.funds.as_slice() {
+        [coin] if coin.denom == market.denom && !coin.amount.is_zero() => coin.amount,
+        _ => {
+            return Err(ContractError::InvalidStake {
+                denom: market.denom,
+            })
+        }
+    };
+    if let Some(ballot) = BALLOTS.may_load(storage, (info.sender.clone(), poll_id.to_string()))? {
+        if ballot.option != option {
+            return Err(ContractError::StakeLocked {});
         }
-        None => Err(ContractError::Unauthorized {}), // The poll does not exist so we just error
     }
+
+    let count = market.stakes.len() as u32;
+    *market
+        .stakes
+        .get_mut(option as usize)
+        .ok_or(ContractError::InvalidOptionIndex {
+            index: option,
+            count,
+        })? += amount;
+    MARKETS.save(storage, poll_id.to_string(), &market)?;
+    STAKES.update(
+        storage,
+        (poll_id.to_string(), info.sender.clone()),
+        |stake| -> StdResult<_> { Ok(stake.unwrap_or_default() + amount) },
+    )?;
+    Ok(())
 }
 
-fn execute_create_poll(
+// Relayer trả gas, mỗi ballot được kiểm tra chữ ký và nonce của voter.
+// Cả batch là atomic: một ballot lỗi thì toàn bộ message bị revert
+fn execute_submit_signed_votes(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
-    poll_id: String,
-    question: String,
-    options: Vec<String>,
+    votes: Vec<SignedVote>,
 ) -> Result<Response, ContractError> {
-    if options.len() > 10 {
-        return Err(ContractError::TooManyOptions {});
-    }
+    let count = votes.len();
+    for signed in votes {
+        let voter = deps
+            .api
+            .addr_humanize(&pubkey_to_canonical(&signed.pubkey)?)?;
 
-    let mut opts: Vec<(String, u64)> = vec![];
-    for option in options {
-        opts.push((option, 0));
-    }
+        let expected = NONCES.may_load(deps.storage, voter.clone())?.unwrap_or(0);
+        if signed.nonce != expected {
+            return Err(ContractError::InvalidNonce { expected });
+        }
 
-    let poll = Poll {
-        creator: info.sender,
-        question,
-        options: opts,
-    };
+        let sign_doc = signed_vote_sign_doc(
+            &env.contract.address,
+            &voter,
+            &signed.poll_id,
+            &signed.vote,
+            signed.nonce,
+        )?;
+        let hash = Sha256::digest(sign_doc);
+        if !deps
+            .api
+            .secp256k1_verify(&hash, &signed.signature, &signed.pubkey)?
+        {
+            return Err(ContractError::InvalidSignature {});
+        }
+        NONCES.save(deps.storage, voter.clone(), &(expected + 1))?;
 
-    POLLS.save(deps.storage, poll_id, &poll)?;
+        let poll = load_poll(deps.storage, &signed.poll_id)?;
+        if poll.voting_mode != VotingMode::Public {
+            return Err(ContractError::WrongVotingMode {});
+        }
+        check_not_ended(&poll, &env)?;
+        // ballot ký off-chain không kèm được stake
+        if let Some(market) = MARKETS.may_load(deps.storage, signed.poll_id.clone())? {
+            return Err(ContractError::InvalidStake {
+                denom: market.denom,
+            });
+        }
+        let option = option_index(deps.storage, &signed.poll_id, &signed.vote)?;
+        tally_vote(
+            deps.storage,
+            &env.block,
+            signed.poll_id,
+            &poll,
+            voter,
+            option,
+        )?;
+    }
 
-    Ok(Response::new())
+    Ok(Response::new()
+        .add_attribute("action", "submit_signed_votes")
+        .add_attribute("relayer", info.sender)
+        .add_attribute("count", count.to_string()))
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(_deps: Deps, _env: Env, _msg: QueryMsg) -> StdResult<Binary> {
-    match _msg {
-        QueryMsg::AllPoll {} => query_all_polls(_deps, _env),
-        QueryMsg::Poll { poll_id } => query_poll(_deps, _env, poll_id),
-        QueryMsg::Vote { poll_id, address } => query_vote(_deps, _env, address, poll_id),
+// Address cosmos chuẩn: ripemd160(sha256(pubkey)), prefix bech32 do chain quyết định qua addr_humanize
+// chỉ nhận pubkey nén 33 byte, key 65 byte sẽ ra address khác với address của voter
+fn pubkey_to_canonical(pubkey: &[u8]) -> Result<CanonicalAddr, ContractError> {
+    if pubkey.len() != 33 {
+        return Err(ContractError::InvalidSignature {});
     }
-    //unimplemented!()
+    let hash = Ripemd160::digest(Sha256::digest(pubkey));
+    Ok(CanonicalAddr::from(hash.to_vec()))
 }
 
-fn query_all_polls(deps: Deps, _env: Env) -> StdResult<Binary> {
-    let polls = POLLS
-        .range(deps.storage, None, None, Order::Ascending)
-        .map(|p| Ok(p?.1))
-        .collect::<StdResult<Vec<_>>>()?;
+fn check_not_ended(poll: &Poll, env: &Env) -> Result<(), ContractError> {
+    match poll.end_height {
+        Some(end_height) if env.block.height > end_height => Err(ContractError::PollNotOpen {}),
+        _ => Ok(()),
+    }
+}
 
-    // đoạn trên tương đương
-    // let polls_iter = POLLS
-    //     .range(deps.storage, None, None, Order::Ascending)
-    //     .map(|p| Ok(p?.1));
-    // let polls: StdResult<Vec<_>> = polls_iter.collect(); // Chỉ thực thi tại đây
-    // -> chưa unwrap
-    // let polls: Vec<_> = polls_iter.collect()?; // Unwrap ra Vec, nếu có lỗi sẽ trả về lỗi ngay lập tức
+fn is_eligible(storage: &dyn Storage, poll: &Poll, voter: &Addr) -> StdResult<bool> {
+    Ok(match poll.eligibility {
+        Eligibility::Anyone => true,
+        Eligibility::Electorate => ELECTORATE.has(storage, voter.clone()),
+    })
+}
 
-    // POLLS.range(...): Trả về một iterator chứa từng mục (key, value) trong storage.
-    // None, None: Không có giới hạn dưới và trên → lấy toàn bộ dữ liệu.
-    // Order::Ascending: Lấy dữ liệu theo thứ tự tăng dần của key.
+// Lưu ballot của voter và cập nhật số phiếu trong TALLIES
+// dùng chung cho vote công khai và reveal
+fn tally_vote(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    poll_id: String,
+    poll: &Poll,
+    voter: Addr,
+    option: u32,
+) -> Result<(), ContractError> {
+    if poll.status != PollStatus::Open {
+        return Err(ContractError::PollNotOpen {});
+    }
+    if !is_eligible(storage, poll, &voter)? {
+        return Err(ContractError::NotEligible {});
+    }
+    if option >= poll.option_count {
+        return Err(ContractError::InvalidOptionIndex {
+            index: option,
+            count: poll.option_count,
+        });
+    }
 
-    // .map(|p| Ok(p?.1))
-    //  p là một Result<(Key, Value), StdError>. Do .range() khi dùng với storage trả về iterator chứa phần tử Result<(key, value), StdError>
-    //  p?: Nếu p là Err, thoát khỏi hàm và trả lỗi.
-    // .1: Lấy phần Value (bỏ qua Key)
+    let key = (voter.clone(), poll_id.clone());
+    match BALLOTS.may_load(storage, key.clone())? {
+        // vote lại đúng option cũ: không đổi gì (market chỉ cộng thêm stake),
+        // riêng poll Immutable thì báo lỗi vì không cho vote lần hai
+        Some(ballot) if ballot.option == option => {
+            if poll.vote_change == VoteChangePolicy::Immutable && !MARKETS.has(storage, poll_id) {
+                return Err(ContractError::AlreadyVoted {});
+            }
+            return Ok(());
+        }
+        // trường hợp đã vote rồi giờ muốn vote lại
+        // We need to revoke their old vote
+        Some(ballot) => {
+            check_vote_change(poll, block)?;
+            TALLIES.update(
+                storage,
+                (poll_id.clone(), ballot.option),
+                block.height,
+                |tally| Ok::<_, StdError>(tally.unwrap_or_default().saturating_sub(Uint128::one())),
+            )?;
+        }
+        // index voter theo poll cho lần vote đầu tiên
+        None => {
+            POLL_VOTERS.save(storage, (poll_id.clone(), voter.clone()), &())?;
+            let turnout = record_turnout(storage, &poll_id, block.height, true)?;
+            let mut stats = STATS.may_load(storage)?.unwrap_or_default();
+            stats.total_ballots += 1;
+            if !VOTERS.has(storage, voter.clone()) {
+                VOTERS.save(storage, voter, &())?;
+                stats.unique_voters += 1;
+            }
+            rank_poll(&mut stats, &poll_id, turnout);
+            STATS.save(storage, &stats)?;
+        }
+    }
+    BALLOTS.save(storage, key, &Ballot { option })?; //update vote mới
 
-    // .collect::<StdResult<Vec<_>>>()?
-    // Mục tiêu: Chuyển iterator thành Vec<Value>, nhưng vẫn giữ lỗi nếu có. lưu các value vào Vec
-    // StdResult<Vec<_>>:
-    // Ok(Vec<Value>) nếu tất cả phần tử thành công.
-    // Err(StdError) nếu có lỗi.
-    // ?: Nếu có lỗi, dừng và trả lỗi ngay lập tức.
+    // Increment the new vote option by 1
+    TALLIES.update(storage, (poll_id, option), block.height, |tally| {
+        Ok::<_, StdError>(tally.unwrap_or_default() + Uint128::one())
+    })?;
+    Ok(())
+}
 
-    to_binary(&AllPollsResponse { polls })
+// Đổi phiếu đã có theo VoteChangePolicy của poll
+fn check_vote_change(poll: &Poll, block: &BlockInfo) -> Result<(), ContractError> {
+    match &poll.vote_change {
+        VoteChangePolicy::Immutable => Err(ContractError::VoteChangeNotAllowed {}),
+        VoteChangePolicy::ChangeableUntil(deadline) if deadline.is_passed(block) => {
+            Err(ContractError::VoteChangeNotAllowed {})
+        }
+        _ => Ok(()),
+    }
 }
 
-fn query_poll(deps: Deps, env: Env, poll_id: String) -> StdResult<Binary> {
-    let poll = POLLS.may_load(deps.storage, poll_id)?;
+// Xoá ballot của voter và trừ phiếu khỏi TALLIES
+fn execute_retract_vote(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: String,
+) -> Result<Response, ContractError> {
+    let poll = load_poll(deps.storage, &poll_id)?;
+    if poll.vote_change != VoteChangePolicy::Retractable {
+        return Err(ContractError::VoteChangeNotAllowed {});
+    }
+    // poll commit-reveal chỉ rút được tới hết reveal phase
+    if !matches!(
+        poll_phase(&poll, env.block.height),
+        PollPhase::Voting | PollPhase::Revealing
+    ) || poll.status != PollStatus::Open
+    {
+        return Err(ContractError::PollNotOpen {});
+    }
+    // stake trong market không rút ra được
+    if MARKETS.has(deps.storage, poll_id.clone()) {
+        return Err(ContractError::StakeLocked {});
+    }
 
-    to_binary(&PollResponse { poll })
-    //unimplemented!()
+    let key = (info.sender.clone(), poll_id.clone());
+    let ballot = BALLOTS
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::NoBallot {})?;
+    BALLOTS.remove(deps.storage, key);
+    POLL_VOTERS.remove(deps.storage, (poll_id.clone(), info.sender.clone()));
+    TALLIES.update(
+        deps.storage,
+        (poll_id.clone(), ballot.option),
+        env.block.height,
+        |tally| Ok::<_, StdError>(tally.unwrap_or_default().saturating_sub(Uint128::one())),
+    )?;
+    let turnout = record_turnout(deps.storage, &poll_id, env.block.height, false)?;
+    let mut stats = STATS.may_load(deps.storage)?.unwrap_or_default();
+    stats.total_ballots = stats.total_ballots.saturating_sub(1);
+    rank_poll(&mut stats, &poll_id, turnout);
+    STATS.save(deps.storage, &stats)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "retract_vote")
+        .add_attribute("poll_id", poll_id)
+        .add_attribute("voter", info.sender))
 }
 
-fn query_vote(deps: Deps, env: Env, address: String, poll_id: String) -> StdResult<Binary> {
-    let validated_address = deps.api.addr_validate(&address)?;
-    let vote = BALLOTS.may_load(deps.storage, (validated_address, poll_id))?;
-    to_binary(&VoteResponse { vote })
-    //unimplemented!();
+// index của option có label `vote`, poll tối đa 10 option nên duyệt thẳng
+fn option_index(storage: &dyn Storage, poll_id: &str, vote: &str) -> Result<u32, ContractError> {
+    OPTIONS
+        .prefix(poll_id.to_string())
+        .range(storage, None, None, Order::Ascending)
+        .find_map(|item| match item {
+            Ok((index, option)) if option.label == vote => Some(Ok(index)),
+            Ok(_) => None,
+            Err(err) => Some(Err(err.into())),
+        })
+        .unwrap_or_else(|| {
+            Err(ContractError::InvalidOption {
+                option: vote.to_string(),
+            })
+        })
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
-    unimplemented!()
+// Lưu lại toàn bộ option của poll, xoá các option cũ nếu có
+fn save_options(
+    storage: &mut dyn Storage,
+    height: u64,
+    poll_id: &str,
+    poll: &mut Poll,
+    labels: Vec<String>,
+    metadata: Vec<OptionMetadata>,
+) -> StdResult<()> {
+    for index in 0..poll.option_count {
+        OPTIONS.remove(storage, (poll_id.to_string(), index));
+        TALLIES.remove(storage, (poll_id.to_string(), index), height)?;
+    }
+    poll.option_count = labels.len() as u32;
+    for (index, label) in labels.into_iter().enumerate() {
+        let meta = metadata.get(index).cloned().unwrap_or_default();
+        OPTIONS.save(
+            storage,
+            (poll_id.to_string(), index as u32),
+            &OptionInfo {
+                label,
+                description: meta.description,
+                uri: meta.uri,
+            },
+        )?;
+    }
+    Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use cosmwasm_std::{attr, from_binary};
-    //module attr, helper mod
-    //tạo và sử dụng các thuộc tính(attributes)
-    // e.g. : ("action", "instantiate")
+// Cập nhật số voter của poll tại block hiện tại, mỗi block chỉ giữ một checkpoint
+fn record_turnout(
+    storage: &mut dyn Storage,
+    poll_id: &str,
+    height: u64,
+    joined: bool,
+) -> StdResult<u64> {
+    let voters = TURNOUT
+        .prefix(poll_id.to_string())
+        .range(storage, None, None, Order::Descending)
+        .next()
+        .transpose()?
+        .map(|(_, voters)| voters)
+        .unwrap_or(0);
+    let voters = if joined {
+        voters + 1
+    } else {
+        voters.saturating_sub(1)
+    };
+    TURNOUT.save(storage, (poll_id.to_string(), height), &voters)?;
+    Ok(voters)
+}
 
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    //các hàm giả lập (mock function to mock an envirionment, message info, dependencies)
-    //mock_dependencies tạo ra một đối tượng giả lập cho các Deps trong môi trường CosmWasm. Nó bao gồm các phần như bộ lưu trữ (storage), API, và querier mà hợp đồng sẽ sử dụng. Điều này giúp bạn kiểm tra các hành động như ghi dữ liệu vào bộ lưu trữ mà không cần một blockchain thật.
-    //mock_env tạo ra một đối tượng giả lập cho môi trường (Env) mà hợp đồng thông minh chạy trong đó. Nó bao gồm các thông tin như thời gian, địa chỉ của người gọi, và các yếu tố khác liên quan đến môi trường thực thi.
-    //mock_info giúp tạo ra thông tin giả lập cho MessageInfo, bao gồm địa chỉ người gọi và các tiền tệ gửi kèm (nếu có). Đây là đối tượng chứa các thông tin về người gửi giao dịch
+// Cập nhật vị trí của poll trong bảng top turnout
+fn rank_poll(stats: &mut Stats, poll_id: &str, turnout: u64) {
+    stats.top_polls.retain(|(id, _)| id != poll_id);
+    let position = stats
+        .top_polls
+        .iter()
+        .position(|(_, voters)| *voters < turnout)
+        .unwrap_or(stats.top_polls.len());
+    if position < TOP_POLLS_LEN && turnout > 0 {
+        stats
+            .top_polls
+            .insert(position, (poll_id.to_string(), turnout));
+        stats.top_polls.truncate(TOP_POLLS_LEN);
+    }
+}
 
-    use crate::contract::{execute, instantiate}; // hàm init của contract
-    use crate::msg::{
-        AllPollsResponse, ExecuteMsg, InstantiateMsg, PollResponse, QueryMsg, VoteResponse,
-    };
+fn load_options(storage: &dyn Storage, poll_id: &str) -> StdResult<Vec<OptionInfo>> {
+    OPTIONS
+        .prefix(poll_id.to_string())
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| Ok(item?.1))
+        .collect()
+}
 
-    use super::query; //
+// (label, số phiếu trực tiếp) theo thứ tự index
+fn load_tally(storage: &dyn Storage, poll_id: &str) -> StdResult<Vec<(String, u64)>> {
+    load_options(storage, poll_id)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, option)| {
+            let tally = TALLIES
+                .may_load(storage, (poll_id.to_string(), index as u32))?
+                .unwrap_or_default();
+            Ok((option.label, tally.u128() as u64))
+        })
+        .collect()
+}
 
-    //các account giả lập
-    pub const ADDR1: &str = "cosmos1g84934fjawef9w4vl4um68mjhuhlx5xstt0whp";
-    pub const ADDR2: &str = "addr2";
+fn execute_commit_vote(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: String,
+    commitment: Binary,
+) -> Result<Response, ContractError> {
+    let poll = load_poll(deps.storage, &poll_id)?;
+    if poll.status != PollStatus::Open {
+        return Err(ContractError::PollNotOpen {});
+    }
+    if !is_eligible(deps.storage, &poll, &info.sender)? {
+        return Err(ContractError::NotEligible {});
+    }
 
-    #[test]
-    fn test_query_all_polls() {
-        let mut deps = mock_dependencies();
-        let env = mock_env();
-        let info = mock_info(ADDR1, &vec![]);
+    match poll.voting_mode {
+        VotingMode::CommitReveal {
+            commit_end_height, ..
+        } => {
+            if env.block.height > commit_end_height {
+                return Err(ContractError::CommitPhaseClosed {});
+            }
+        }
+        VotingMode::Public => return Err(ContractError::WrongVotingMode {}),
+    }
 
-        let msg = InstantiateMsg { admin: None };
-        let res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg);
+    // Trong commit phase được commit lại (ghi đè commitment cũ)
+    COMMITMENTS.save(
+        deps.storage,
+        (poll_id.clone(), info.sender.clone()),
+        &Commitment {
+            hash: commitment,
+            revealed: false,
+        },
+    )?;
 
-        let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id_1".to_string(),
-            question: "jdksjkkdj".to_string(),
-            options: vec![
-                "Cosmos Hub".to_string(),
-                "Juno".to_string(),
-                "Osmosis".to_string(),
-            ],
-        };
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    Ok(Response::new()
+        .add_attribute("action", "commit_vote")
+        .add_attribute("poll_id", poll_id)
+        .add_attribute("voter", info.sender))
+}
 
-        let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id_2".to_string(),
-            question: "What's your colour?".to_string(),
-            options: vec!["Red".to_string(), "Green".to_string(), "Blue".to_string()],
-        };
-        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+fn execute_reveal_vote(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: String,
+    vote: VoteOption,
+    salt: String,
+) -> Result<Response, ContractError> {
+    let poll = load_poll(deps.storage, &poll_id)?;
 
-        let msg = QueryMsg::AllPoll {};
-        let bin = query(deps.as_ref(), env, msg).unwrap();
-        let res: AllPollsResponse = from_binary(&bin).unwrap();
+    match poll.voting_mode {
+        VotingMode::CommitReveal {
+            commit_end_height,
+            reveal_end_height,
+        } => {
+            if env.block.height <= commit_end_height || env.block.height > reveal_end_height {
+                return Err(ContractError::NotInRevealPhase {});
+            }
+        }
+        VotingMode::Public => return Err(ContractError::WrongVotingMode {}),
+    }
 
-        assert_eq!(res.polls.len(), 2);
+    let key = (poll_id.clone(), info.sender.clone());
+    let mut commitment = COMMITMENTS
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::NoCommitment {})?;
+    if commitment.revealed {
+        return Err(ContractError::AlreadyRevealed {});
+    }
+    // commitment cam kết theo index nên label phải đổi ra index trước
+    let option = match vote {
+        VoteOption::Index(index) => index,
+        VoteOption::Label(label) => option_index(deps.storage, &poll_id, &label)?,
+    };
+    if compute_commitment(option, &salt, &info.sender) != commitment.hash {
+        return Err(ContractError::CommitmentMismatch {});
     }
 
-    #[test]
-    fn test_query_poll() {
-        let mut deps = mock_dependencies();
-        let env = mock_env();
-        let info = mock_info(ADDR1, &vec![]);
-        // Instantiate the contract
-        let msg = InstantiateMsg { admin: None };
-        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    commitment.revealed = true;
+    COMMITMENTS.save(deps.storage, key, &commitment)?;
+    tally_vote(
+        deps.storage,
+        &env.block,
+        poll_id.clone(),
+        &poll,
+        info.sender.clone(),
+        option,
+    )?;
 
-        // Create a poll
-        let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id_1".to_string(),
-            question: "What's your favourite Cosmos coin?".to_string(),
-            options: vec![
-                "Cosmos Hub".to_string(),
-                "Juno".to_string(),
-                "Osmosis".to_string(),
-            ],
-        };
-        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    Ok(Response::new()
+        .add_attribute("action", "reveal_vote")
+        .add_attribute("poll_id", poll_id)
+        .add_attribute("voter", info.sender))
+}
 
-        // Query for the poll that exists
-        let msg = QueryMsg::Poll {
-            poll_id: "some_id_1".to_string(),
-        };
-        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
-        let res: PollResponse = from_binary(&bin).unwrap();
-        // Expect a poll
-        assert!(res.poll.is_some());
+fn execute_delegate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    to: String,
+    poll_id: Option<String>,
+) -> Result<Response, ContractError> {
+    let delegate = deps.api.addr_validate(&to)?;
+    if let Some(poll_id) = &poll_id {
+        load_poll(deps.storage, poll_id)?;
+    }
 
-        // Query for the poll that does not exists
-        let msg = QueryMsg::Poll {
-            poll_id: "some_id_not_exist".to_string(),
-        };
-        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
-        let res: PollResponse = from_binary(&bin).unwrap();
-        // Expect none
-        assert!(res.poll.is_none());
+    let path = check_delegation_path(deps.storage, &info.sender, &delegate, poll_id.as_deref())?;
+    if poll_id.is_none() {
+        // delegation global còn được dùng trong mọi poll mà sender không có delegation riêng,
+        // chuỗi trong poll đó chỉ rẽ khỏi chuỗi global tại node có delegation riêng cho poll
+        let mut scopes = BTreeSet::new();
+        for addr in &path {
+            for scope in
+                DELEGATIONS
+                    .prefix(addr.clone())
+                    .keys(deps.storage, None, None, Order::Ascending)
+            {
+                let scope = scope?;
+                if scope != GLOBAL_SCOPE
+                    && !DELEGATIONS.has(deps.storage, (info.sender.clone(), scope.clone()))
+                {
+                    scopes.insert(scope);
+                }
+            }
+        }
+        for scope in scopes {
+            check_delegation_path(deps.storage, &info.sender, &delegate, Some(&scope))?;
+        }
     }
 
-    // Previous code omitted
-    #[test]
-    fn test_query_vote() {
-        let mut deps = mock_dependencies();
-        let env = mock_env();
-        let info = mock_info(ADDR1, &vec![]);
-        // Instantiate the contract
-        let msg = InstantiateMsg { admin: None };
-        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    let scope = poll_id.unwrap_or_else(|| GLOBAL_SCOPE.to_string());
+    // xoá index ngược của delegate cũ (nếu có)
+    if let Some(old) = DELEGATIONS.may_load(deps.storage, (info.sender.clone(), scope.clone()))? {
+        DELEGATORS.remove(deps.storage, (old, scope.clone(), info.sender.clone()));
+    }
+    DELEGATIONS.save(
+        deps.storage,
+        (info.sender.clone(), scope.clone()),
+        &delegate,
+    )?;
+    DELEGATORS.save(
+        deps.storage,
+        (delegate.clone(), scope.clone(), info.sender.clone()),
+        &(),
+    )?;
+    if scope != GLOBAL_SCOPE {
+        POLL_DELEGATIONS.save(deps.storage, (scope.clone(), info.sender.clone()), &())?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "delegate")
+        .add_attribute("delegator", info.sender)
+        .add_attribute("delegate", delegate)
+        .add_attribute("scope", scope))
+}
+
+fn execute_undelegate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    poll_id: Option<String>,
+) -> Result<Response, ContractError> {
+    let scope = poll_id.unwrap_or_else(|| GLOBAL_SCOPE.to_string());
+    let delegate = DELEGATIONS
+        .may_load(deps.storage, (info.sender.clone(), scope.clone()))?
+        .ok_or(ContractError::NoDelegation {})?;
+
+    if scope != GLOBAL_SCOPE {
+        // bỏ delegation riêng thì delegation global lại có hiệu lực trong poll này
+        if let Some(global) = delegate_of(deps.storage, &info.sender, None)? {
+            check_delegation_path(deps.storage, &info.sender, &global, Some(&scope))?;
+        }
+    }
+
+    DELEGATIONS.remove(deps.storage, (info.sender.clone(), scope.clone()));
+    DELEGATORS.remove(deps.storage, (delegate, scope.clone(), info.sender.clone()));
+    POLL_DELEGATIONS.remove(deps.storage, (scope.clone(), info.sender.clone()));
+
+    Ok(Response::new()
+        .add_attribute("action", "undelegate")
+        .add_attribute("delegator", info.sender)
+        .add_attribute("scope", scope))
+}
+
+// Đi theo chuỗi delegate bắt đầu từ `start`, nếu quay lại `delegator` thì là vòng lặp.
+// Trả về các address trên chuỗi.
+fn check_delegation_path(
+    storage: &dyn Storage,
+    delegator: &Addr,
+    start: &Addr,
+    poll_id: Option<&str>,
+) -> Result<Vec<Addr>, ContractError> {
+    let mut visited = vec![];
+    let mut current = Some(start.clone());
+    while let Some(addr) = current {
+        if addr == delegator || visited.contains(&addr) {
+            return Err(ContractError::DelegationCycle {});
+        }
+        current = delegate_of(storage, &addr, poll_id)?;
+        visited.push(addr);
+    }
+    Ok(visited)
+}
+
+// Delegate hiện tại của `addr`: trong một poll thì delegation riêng của poll được ưu tiên hơn global
+fn delegate_of(
+    storage: &dyn Storage,
+    addr: &Addr,
+    poll_id: Option<&str>,
+) -> StdResult<Option<Addr>> {
+    if let Some(poll_id) = poll_id {
+        let delegate = DELEGATIONS.may_load(storage, (addr.clone(), poll_id.to_string()))?;
+        if delegate.is_some() {
+            return Ok(delegate);
+        }
+    }
+    DELEGATIONS.may_load(storage, (addr.clone(), GLOBAL_SCOPE.to_string()))
+}
+
+// Kết quả tally có tính delegation
+struct Tally {
+    options: Vec<(String, u64)>,
+    // số phiếu đến từ delegation (đã nằm trong options)
+    delegated: u64,
+}
+
+// Tính số phiếu của poll có cộng thêm phiếu uỷ quyền.
+// Người uỷ quyền mà tự vote thì phiếu của họ đã nằm trong TALLIES (override),
+// còn lại phiếu đi theo chuỗi delegate tới người đầu tiên đã vote.
+// Đi ngược DELEGATORS từ các voter của poll nên không phải đọc toàn bộ DELEGATIONS.
+fn compute_tally(storage: &dyn Storage, poll_id: &str, poll: &Poll) -> StdResult<Tally> {
+    let mut options = load_tally(storage, poll_id)?;
+    let mut delegated = 0;
+
+    let voters = POLL_VOTERS
+        .prefix(poll_id.to_string())
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for voter in voters {
+        let count = count_delegated(storage, poll_id, poll, &voter)?;
+        if count == 0 {
+            continue;
+        }
+        let ballot = BALLOTS.load(storage, (voter.clone(), poll_id.to_string()))?;
+        if let Some(option) = options.get_mut(ballot.option as usize) {
+            option.1 += count;
+            delegated += count;
+        }
+    }
+
+    Ok(Tally { options, delegated })
+}
+
+// Số phiếu uỷ quyền chảy tới `voter` trong poll: đi ngược các delegation đang có hiệu lực,
+// dừng ở người đã tự vote vì phiếu của họ (và người uỷ quyền cho họ) thuộc về chính họ
+fn count_delegated(
+    storage: &dyn Storage,
+    poll_id: &str,
+    poll: &Poll,
+    voter: &Addr,
+) -> StdResult<u64> {
+    let mut count = 0;
+    let mut visited = BTreeSet::from([voter.clone()]);
+    let mut stack = vec![voter.clone()];
+    while let Some(addr) = stack.pop() {
+        for scope in [poll_id, GLOBAL_SCOPE] {
+            let delegators = DELEGATORS
+                .prefix((addr.clone(), scope.to_string()))
+                .keys(storage, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<_>>>()?;
+            for delegator in delegators {
+                // delegation global bị thay thế bởi delegation riêng của poll
+                if scope == GLOBAL_SCOPE
+                    && DELEGATIONS.has(storage, (delegator.clone(), poll_id.to_string()))
+                {
+                    continue;
+                }
+                if !visited.insert(delegator.clone()) {
+                    continue;
+                }
+                // tự vote -> override delegation
+                if BALLOTS.has(storage, (delegator.clone(), poll_id.to_string())) {
+                    continue;
+                }
+                // người không được vote thì không có phiếu, nhưng chuỗi vẫn đi qua họ
+                if is_eligible(storage, poll, &delegator)? {
+                    count += 1;
+                }
+                stack.push(delegator);
+            }
+        }
+    }
+    Ok(count)
+}
+
+fn check_len(field: &str, value: &Option<String>, max: usize) -> Result<(), ContractError> {
+    match value {
+        Some(value) if value.len() > max => Err(ContractError::MetadataTooLong {
+            field: field.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn validate_metadata(
+    config: &Config,
+    metadata: &PollMetadata,
+    option_metadata: &[OptionMetadata],
+    options_len: usize,
+) -> Result<(), ContractError> {
+    check_len("description", &metadata.description, MAX_DESCRIPTION_LEN)?;
+    check_len("external_url", &metadata.external_url, MAX_URI_LEN)?;
+    check_len("image_uri", &metadata.image_uri, MAX_URI_LEN)?;
+    check_len("category", &metadata.category, MAX_CATEGORY_LEN)?;
+    if let Some(category) = &metadata.category {
+        if !config.categories.is_empty() && !config.categories.contains(category) {
+            return Err(ContractError::InvalidCategory {});
+        }
+    }
+    if metadata.tags.len() > MAX_TAGS {
+        return Err(ContractError::InvalidMetadata {});
+    }
+    for tag in &metadata.tags {
+        if tag.len() > MAX_TAG_LEN {
+            return Err(ContractError::MetadataTooLong {
+                field: "tags".to_string(),
+            });
+        }
+    }
+
+    // không có metadata cho option thì để rỗng
+    if !option_metadata.is_empty() && option_metadata.len() != options_len {
+        return Err(ContractError::InvalidMetadata {});
+    }
+    for option in option_metadata {
+        check_len(
+            "option_description",
+            &option.description,
+            MAX_DESCRIPTION_LEN,
+        )?;
+        check_len("option_uri", &option.uri, MAX_URI_LEN)?;
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn execute_create_poll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: String,
+    question: String,
+    options: Vec<String>,
+    voting_mode: Option<VotingMode>,
+    reward_split: Option<RewardSplit>,
+    market: Option<MarketParams>,
+    eligibility: Option<Eligibility>,
+    metadata: Option<PollMetadata>,
+    option_metadata: Option<Vec<OptionMetadata>>,
+    vote_change: Option<VoteChangePolicy>,
+    duration: Option<u64>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    // poll của series do chính contract tạo, không cần quyền và tiền cọc
+    let internal = info.sender == env.contract.address;
+    if config.restricted_creation
+        && !internal
+        && !has_role(deps.storage, &config, &info.sender, Role::PollCreator)?
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+    // '#' dành cho poll_id của series, không cho user chiếm trước id của instance sau
+    if poll_id.is_empty() || (!internal && poll_id.contains('#')) {
+        return Err(ContractError::InvalidPollId {});
+    }
+    // không cho ghi đè poll đã có (kể cả poll đã xoá)
+    if POLLS.has(deps.storage, poll_id.clone()) || POLL_RESULTS.has(deps.storage, poll_id.clone()) {
+        return Err(ContractError::PollAlreadyExists { poll_id });
+    }
+    if options.len() > 10 {
+        return Err(ContractError::TooManyOptions {});
+    }
+    let metadata = metadata.unwrap_or_default();
+    let option_metadata = option_metadata.unwrap_or_default();
+    validate_metadata(&config, &metadata, &option_metadata, options.len())?;
+
+    let voting_mode = voting_mode.unwrap_or_default();
+    if let VotingMode::CommitReveal {
+        commit_end_height,
+        reveal_end_height,
+    } = voting_mode
+    {
+        // commit phase phải còn ở tương lai và reveal phase nằm sau nó,
+        // duration chỉ dùng cho poll công khai
+        if commit_end_height <= env.block.height
+            || reveal_end_height <= commit_end_height
+            || duration.is_some()
+        {
+            return Err(ContractError::InvalidPhases {});
+        }
+    }
+
+    if let Some(market) = market {
+        // stake phải công khai để chia tiền, không dùng với commit-reveal
+        if voting_mode != VotingMode::Public {
+            return Err(ContractError::WrongVotingMode {});
+        }
+        if market.close_height <= env.block.height {
+            return Err(ContractError::InvalidPhases {});
+        }
+        MARKETS.save(
+            deps.storage,
+            poll_id.clone(),
+            &Market {
+                resolver: deps.api.addr_validate(&market.resolver)?,
+                denom: market.denom,
+                stakes: vec![Uint128::zero(); options.len()],
+                status: MarketStatus::Open,
+                close_height: market.close_height,
+                claimed_stake: Uint128::zero(),
+                paid: Uint128::zero(),
+            },
+        )?;
+    }
+
+    // funds = tiền cọc + quỹ thưởng (nếu có)
+    let mut funds = info.funds.clone();
+    if let Some(deposit) = config.poll_deposit.filter(|_| !internal) {
+        let paid = funds
+            .iter_mut()
+            .find(|coin| coin.denom == deposit.denom && coin.amount >= deposit.amount)
+            .ok_or(ContractError::InvalidDeposit {
+                expected: deposit.to_string(),
+            })?;
+        paid.amount -= deposit.amount;
+        funds.retain(|coin| !coin.amount.is_zero());
+        // không tạo reward pool thì phải gửi kèm đúng số tiền cọc
+        if reward_split.is_none() && !funds.is_empty() {
+            return Err(ContractError::InvalidDeposit {
+                expected: deposit.to_string(),
+            });
+        }
+        DEPOSITS.save(
+            deps.storage,
+            poll_id.clone(),
+            &Deposit {
+                depositor: info.sender.clone(),
+                amount: deposit,
+            },
+        )?;
+    }
+
+    // không có cọc và không có quỹ thưởng thì không nhận tiền gửi kèm
+    if reward_split.is_none() && !funds.is_empty() {
+        return Err(ContractError::UnexpectedFunds {});
+    }
+
+    if let Some(split) = reward_split {
+        if funds.len() != 1 || funds[0].amount.is_zero() {
+            return Err(ContractError::InvalidRewardPool {});
+        }
+        REWARD_POOLS.save(
+            deps.storage,
+            poll_id.clone(),
+            &RewardPool {
+                funds: funds[0].clone(),
+                split,
+                total_weight: 0,
+                settle_cursor: None,
+                settled: false,
+                claimed_weight: 0,
+                paid: Uint128::zero(),
+            },
+        )?;
+    }
+
+    let mut poll = Poll {
+        creator: info.sender,
+        question,
+        option_count: 0,
+        voting_mode,
+        status: PollStatus::Open,
+        eligibility: eligibility.unwrap_or_default(),
+        hidden: false,
+        reports: 0,
+        metadata,
+        revision: 0,
+        end_height: duration.map(|duration| env.block.height + duration),
+        vote_change: vote_change.unwrap_or_default(),
+        closed_height: None,
+    };
+
+    save_options(
+        deps.storage,
+        env.block.height,
+        &poll_id,
+        &mut poll,
+        options,
+        option_metadata,
+    )?;
+    save_revision(deps.storage, &poll_id, &poll, env.block.height)?;
+    index_poll(deps.storage, &poll_id, &poll)?;
+    CREATED_POLLS.save(deps.storage, (poll.creator.clone(), poll_id.clone()), &())?;
+    POLLS.save(deps.storage, poll_id, &poll)?;
+
+    let mut stats = STATS.may_load(deps.storage)?.unwrap_or_default();
+    stats.total_polls += 1;
+    stats.open_polls += 1;
+    STATS.save(deps.storage, &stats)?;
+
+    Ok(Response::new())
+}
+
+// Chữ thường, chỉ giữ chữ và số, gộp khoảng trắng
+fn normalize(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn question_key(poll_id: &str, question: &str) -> String {
+    format!("{}:{}", normalize(question), poll_id)
+}
+
+fn index_poll(storage: &mut dyn Storage, poll_id: &str, poll: &Poll) -> StdResult<()> {
+    for tag in &poll.metadata.tags {
+        TAG_INDEX.save(storage, (normalize(tag), poll_id.to_string()), &())?;
+    }
+    QUESTION_INDEX.save(
+        storage,
+        question_key(poll_id, &poll.question),
+        &poll_id.to_string(),
+    )
+}
+
+fn unindex_poll(storage: &mut dyn Storage, poll_id: &str, poll: &Poll) {
+    for tag in &poll.metadata.tags {
+        TAG_INDEX.remove(storage, (normalize(tag), poll_id.to_string()));
+    }
+    QUESTION_INDEX.remove(storage, question_key(poll_id, &poll.question));
+}
+
+fn execute_save_template(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    template_id: String,
+    template: Option<Template>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !has_role(deps.storage, &config, &info.sender, Role::Owner)? {
+        return Err(ContractError::Unauthorized {});
+    }
+    match template {
+        Some(template) => {
+            if template.options.len() > 10 {
+                return Err(ContractError::TooManyOptions {});
+            }
+            // duration chỉ dùng cho poll công khai, commit-reveal đã có phase riêng
+            if let TemplateVotingMode::CommitReveal {
+                commit_blocks,
+                reveal_blocks,
+            } = template.voting_mode
+            {
+                if commit_blocks == 0 || reveal_blocks == 0 || template.duration.is_some() {
+                    return Err(ContractError::InvalidPhases {});
+                }
+            }
+            TEMPLATES.save(deps.storage, template_id.clone(), &template)?;
+        }
+        None => TEMPLATES.remove(deps.storage, template_id.clone()),
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "save_template")
+        .add_attribute("template_id", template_id))
+}
+
+fn substitute(pattern: &str, params: &BTreeMap<String, String>) -> String {
+    params
+        .iter()
+        .fold(pattern.to_string(), |text, (key, value)| {
+            text.replace(&format!("{{{}}}", key), value)
+        })
+}
+
+fn execute_create_poll_from_template(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    template_id: String,
+    poll_id: String,
+    params: BTreeMap<String, String>,
+) -> Result<Response, ContractError> {
+    let template = TEMPLATES
+        .may_load(deps.storage, template_id.clone())?
+        .ok_or(ContractError::TemplateNotFound {
+            template_id: template_id.clone(),
+        })?;
+
+    let height = env.block.height;
+    let voting_mode = match template.voting_mode {
+        TemplateVotingMode::Public => VotingMode::Public,
+        TemplateVotingMode::CommitReveal {
+            commit_blocks,
+            reveal_blocks,
+        } => VotingMode::CommitReveal {
+            commit_end_height: height + commit_blocks,
+            reveal_end_height: height + commit_blocks + reveal_blocks,
+        },
+    };
+    let question = substitute(&template.question, &params);
+    let options = template
+        .options
+        .iter()
+        .map(|option| substitute(option, &params))
+        .collect();
+
+    let res = execute_create_poll(
+        deps.branch(),
+        env,
+        info,
+        poll_id.clone(),
+        question,
+        options,
+        Some(voting_mode),
+        None,
+        None,
+        Some(template.eligibility),
+        None,
+        None,
+        None,
+        template.duration,
+    )?;
+
+    Ok(res
+        .add_attribute("action", "create_poll_from_template")
+        .add_attribute("template_id", template_id)
+        .add_attribute("poll_id", poll_id))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn execute_create_series(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    series_id: String,
+    template_id: String,
+    params: BTreeMap<String, String>,
+    period: u64,
+    bounty: Option<Coin>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !has_role(deps.storage, &config, &info.sender, Role::Owner)? {
+        return Err(ContractError::Unauthorized {});
+    }
+    if series_id.is_empty() || SERIES.has(deps.storage, series_id.clone()) {
+        return Err(ContractError::InvalidPollId {});
+    }
+    if period == 0 {
+        return Err(ContractError::InvalidPhases {});
+    }
+    // quỹ bounty chỉ nhận một coin cùng denom với bounty
+    let fund = match &bounty {
+        Some(bounty) => match info.funds.as_slice() {
+            [] => Uint128::zero(),
+            [coin] if coin.denom == bounty.denom => coin.amount,
+            _ => {
+                return Err(ContractError::InvalidRewardPool {});
+            }
+        },
+        None if info.funds.is_empty() => Uint128::zero(),
+        None => return Err(ContractError::InvalidRewardPool {}),
+    };
+
+    let mut series = Series {
+        template_id,
+        params,
+        period,
+        bounty,
+        fund,
+        funder: info.sender.clone(),
+        instance: 0,
+        current: None,
+        next_height: env.block.height + period,
+    };
+    let poll_id = open_series_instance(deps.branch(), &env, &series_id, &mut series)?;
+    SERIES.save(deps.storage, series_id.clone(), &series)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "create_series")
+        .add_attribute("series_id", series_id)
+        .add_attribute("poll_id", poll_id))
+}
+
+// Mở poll tiếp theo của series, poll_id = "<series_id>#<instance>"
+fn open_series_instance(
+    mut deps: DepsMut,
+    env: &Env,
+    series_id: &str,
+    series: &mut Series,
+) -> Result<String, ContractError> {
+    series.instance += 1;
+    let poll_id = format!("{}#{}", series_id, series.instance);
+    let mut params = series.params.clone();
+    params.insert("instance".to_string(), series.instance.to_string());
+
+    let info = MessageInfo {
+        sender: env.contract.address.clone(),
+        funds: vec![],
+    };
+    execute_create_poll_from_template(
+        deps.branch(),
+        env.clone(),
+        info,
+        series.template_id.clone(),
+        poll_id.clone(),
+        params,
+    )?;
+    SERIES_HISTORY.save(
+        deps.storage,
+        (series_id.to_string(), series.instance),
+        &SeriesInstance {
+            poll_id: poll_id.clone(),
+            opened_height: env.block.height,
+            closed_height: None,
+        },
+    )?;
+    series.current = Some(poll_id.clone());
+    Ok(poll_id)
+}
+
+fn execute_advance_series(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    series_id: String,
+) -> Result<Response, ContractError> {
+    let mut series = SERIES.load(deps.storage, series_id.clone())?;
+    if env.block.height < series.next_height {
+        return Err(ContractError::SeriesNotDue {
+            next_height: series.next_height,
+        });
+    }
+
+    let messages = close_series_instance(deps.storage, env.block.height, &series_id, &series)?;
+    let poll_id = open_series_instance(deps.branch(), &env, &series_id, &mut series)?;
+    series.next_height = env.block.height + series.period;
+
+    // bounty trả từ quỹ, hết quỹ thì series vẫn chạy nhưng không có thưởng
+    let mut res = Response::new();
+    if let Some(bounty) = &series.bounty {
+        let amount = bounty.amount.min(series.fund);
+        if !amount.is_zero() {
+            series.fund -= amount;
+            res = res.add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![coin(amount.u128(), &bounty.denom)],
+            });
+        }
+    }
+    SERIES.save(deps.storage, series_id.clone(), &series)?;
+
+    Ok(res
+        .add_attribute("action", "advance_series")
+        .add_attribute("series_id", series_id)
+        .add_attribute("poll_id", poll_id)
+        .add_messages(messages))
+}
+
+// Đóng poll hiện tại của series nếu chưa bị finalize / huỷ bằng tay
+fn close_series_instance(
+    storage: &mut dyn Storage,
+    height: u64,
+    series_id: &str,
+    series: &Series,
+) -> StdResult<Vec<BankMsg>> {
+    let mut messages = vec![];
+    if let Some(current) = series.current.clone() {
+        if let Some(mut poll) = POLLS.may_load(storage, current.clone())? {
+            if poll.status == PollStatus::Open {
+                messages = finalize_poll(storage, height, &current, &mut poll)?;
+            }
+        }
+        SERIES_HISTORY.update(
+            storage,
+            (series_id.to_string(), series.instance),
+            |instance| -> StdResult<_> {
+                let mut instance = instance.ok_or_else(|| StdError::not_found("instance"))?;
+                instance.closed_height = Some(height);
+                Ok(instance)
+            },
+        )?;
+    }
+    Ok(messages)
+}
+
+fn execute_fund_series(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    series_id: String,
+) -> Result<Response, ContractError> {
+    let mut series = SERIES.load(deps.storage, series_id.clone())?;
+    let amount = match (&series.bounty, info.funds.as_slice()) {
+        (Some(bounty), [coin]) if coin.denom == bounty.denom && !coin.amount.is_zero() => {
+            coin.amount
+        }
+        _ => return Err(ContractError::InvalidRewardPool {}),
+    };
+    series.fund += amount;
+    SERIES.save(deps.storage, series_id.clone(), &series)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "fund_series")
+        .add_attribute("series_id", series_id)
+        .add_attribute("amount", amount)
+        .add_attribute("fund", series.fund))
+}
+
+fn execute_stop_series(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    series_id: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !has_role(deps.storage, &config, &info.sender, Role::Owner)? {
+        return Err(ContractError::Unauthorized {});
+    }
+    let series = SERIES.load(deps.storage, series_id.clone())?;
+    let messages = close_series_instance(deps.storage, env.block.height, &series_id, &series)?;
+    SERIES.remove(deps.storage, series_id.clone());
+
+    let mut res = Response::new()
+        .add_attribute("action", "stop_series")
+        .add_attribute("series_id", series_id)
+        .add_messages(messages);
+    if let (Some(bounty), false) = (series.bounty, series.fund.is_zero()) {
+        res = res.add_message(BankMsg::Send {
+            to_address: series.funder.to_string(),
+            amount: vec![coin(series.fund.u128(), bounty.denom)],
+        });
+    }
+    Ok(res)
+}
+
+fn save_revision(
+    storage: &mut dyn Storage,
+    poll_id: &str,
+    poll: &Poll,
+    height: u64,
+) -> StdResult<()> {
+    let options = load_options(storage, poll_id)?;
+    POLL_REVISIONS.save(
+        storage,
+        (poll_id.to_string(), poll.revision),
+        &PollRevision {
+            revision: poll.revision,
+            height,
+            question: poll.question.clone(),
+            options: options.iter().map(|o| o.label.clone()).collect(),
+            metadata: poll.metadata.clone(),
+            option_metadata: options
+                .into_iter()
+                .map(|o| OptionMetadata {
+                    description: o.description,
+                    uri: o.uri,
+                })
+                .collect(),
+        },
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn execute_update_poll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: String,
+    question: Option<String>,
+    options: Option<Vec<String>>,
+    metadata: Option<PollMetadata>,
+    option_metadata: Option<Vec<OptionMetadata>>,
+) -> Result<Response, ContractError> {
+    let mut poll = load_poll(deps.storage, &poll_id)?;
+    if info.sender != poll.creator {
+        return Err(ContractError::Unauthorized {});
+    }
+    if poll.status != PollStatus::Open {
+        return Err(ContractError::PollNotOpen {});
+    }
+    // đã có ballot hoặc commitment thì không sửa được nữa
+    let has_ballots = POLL_VOTERS
+        .prefix(poll_id.clone())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+    let has_commitments = COMMITMENTS
+        .prefix(poll_id.clone())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+    if has_ballots || has_commitments {
+        return Err(ContractError::PollHasVotes {});
+    }
+    unindex_poll(deps.storage, &poll_id, &poll);
+
+    if let Some(question) = question {
+        poll.question = question;
+    }
+    let current = load_options(deps.storage, &poll_id)?;
+    let options_changed = options.is_some();
+    let labels = options.unwrap_or_else(|| current.iter().map(|o| o.label.clone()).collect());
+    if labels.len() > 10 {
+        return Err(ContractError::TooManyOptions {});
+    }
+    // đổi option mà không gửi metadata mới thì bỏ metadata cũ
+    let option_metadata = match option_metadata {
+        Some(option_metadata) => option_metadata,
+        None if options_changed => vec![],
+        None => current
+            .into_iter()
+            .map(|o| OptionMetadata {
+                description: o.description,
+                uri: o.uri,
+            })
+            .collect(),
+    };
+    if options_changed {
+        // chưa có stake nào nên chỉ cần đổi số option của market
+        if let Some(mut market) = MARKETS.may_load(deps.storage, poll_id.clone())? {
+            market.stakes = vec![Uint128::zero(); labels.len()];
+            MARKETS.save(deps.storage, poll_id.clone(), &market)?;
+        }
+    }
+    if let Some(metadata) = metadata {
+        poll.metadata = metadata;
+    }
+    let config = CONFIG.load(deps.storage)?;
+    validate_metadata(&config, &poll.metadata, &option_metadata, labels.len())?;
+    save_options(
+        deps.storage,
+        env.block.height,
+        &poll_id,
+        &mut poll,
+        labels,
+        option_metadata,
+    )?;
+
+    poll.revision += 1;
+    save_revision(deps.storage, &poll_id, &poll, env.block.height)?;
+    index_poll(deps.storage, &poll_id, &poll)?;
+    POLLS.save(deps.storage, poll_id.clone(), &poll)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_poll")
+        .add_attribute("poll_id", poll_id)
+        .add_attribute("revision", poll.revision.to_string()))
+}
+
+fn execute_finalize_poll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut poll = load_poll(deps.storage, &poll_id)?;
+    let moderator = has_role(deps.storage, &config, &info.sender, Role::Moderator)?;
+    if info.sender != poll.creator && !moderator {
+        return Err(ContractError::Unauthorized {});
+    }
+    if poll.status != PollStatus::Open {
+        return Err(ContractError::PollNotOpen {});
+    }
+    // creator chỉ lấy lại tiền cọc khi poll đã hết hạn, kết thúc sớm phải do moderator
+    // (nếu không thì tạo rồi finalize ngay sẽ lách được anti-spam)
+    let ended = poll_phase(&poll, env.block.height) == PollPhase::Ended;
+    if !moderator && !ended && DEPOSITS.has(deps.storage, poll_id.clone()) {
+        return Err(ContractError::PollNotEnded {});
+    }
+    // poll commit-reveal chỉ kết thúc được sau reveal phase
+    if let VotingMode::CommitReveal {
+        reveal_end_height, ..
+    } = poll.voting_mode
+    {
+        if env.block.height <= reveal_end_height {
+            return Err(ContractError::PollNotEnded {});
+        }
+    }
+
+    // poll prediction market kết thúc qua ResolvePoll / VoidPoll
+    if MARKETS.has(deps.storage, poll_id.clone()) {
+        return Err(ContractError::PollNotEnded {});
+    }
+
+    let messages = finalize_poll(deps.storage, env.block.height, &poll_id, &mut poll)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "finalize_poll")
+        .add_attribute("poll_id", poll_id)
+        .add_messages(messages))
+}
+
+// Poll kết thúc bình thường: chia quỹ thưởng và trả lại tiền cọc cho creator
+fn finalize_poll(
+    storage: &mut dyn Storage,
+    height: u64,
+    poll_id: &str,
+    poll: &mut Poll,
+) -> StdResult<Vec<BankMsg>> {
+    if poll.status == PollStatus::Open {
+        count_closed(storage)?;
+    }
+    poll.status = PollStatus::Finalized;
+    poll.closed_height = Some(height);
+    POLLS.save(storage, poll_id.to_string(), poll)?;
+
+    let mut messages = vec![];
+    let (_, refund) = settle_reward_pool(storage, poll_id, poll, DEFAULT_SETTLE_LIMIT)?;
+    if let Some(refund) = refund {
+        // không ai vote -> quỹ thưởng trả lại creator
+        messages.push(BankMsg::Send {
+            to_address: poll.creator.to_string(),
+            amount: vec![refund],
+        });
+    }
+    if let Some(deposit) = DEPOSITS.may_load(storage, poll_id.to_string())? {
+        DEPOSITS.remove(storage, poll_id.to_string());
+        messages.push(BankMsg::Send {
+            to_address: deposit.depositor.to_string(),
+            amount: vec![deposit.amount],
+        });
+    }
+    Ok(messages)
+}
+
+fn count_closed(storage: &mut dyn Storage) -> StdResult<()> {
+    let mut stats = STATS.may_load(storage)?.unwrap_or_default();
+    stats.open_polls = stats.open_polls.saturating_sub(1);
+    STATS.save(storage, &stats)
+}
+
+// Poll bị huỷ: quỹ thưởng trả lại creator, stake của market được hoàn lại,
+// tiền cọc gửi tới `slash_to` nếu là spam, ngược lại trả cho creator
+fn cancel_poll(
+    storage: &mut dyn Storage,
+    height: u64,
+    poll_id: &str,
+    poll: &mut Poll,
+    slash_to: Option<&Addr>,
+) -> StdResult<Vec<BankMsg>> {
+    if poll.status == PollStatus::Open {
+        count_closed(storage)?;
+    }
+    poll.status = PollStatus::Cancelled;
+    poll.closed_height = Some(height);
+    POLLS.save(storage, poll_id.to_string(), poll)?;
+
+    let mut messages = vec![];
+    if let Some(pool) = REWARD_POOLS.may_load(storage, poll_id.to_string())? {
+        REWARD_POOLS.remove(storage, poll_id.to_string());
+        messages.push(BankMsg::Send {
+            to_address: poll.creator.to_string(),
+            amount: vec![pool.funds],
+        });
+    }
+    if let Some(mut market) = MARKETS.may_load(storage, poll_id.to_string())? {
+        market.status = MarketStatus::Voided;
+        MARKETS.save(storage, poll_id.to_string(), &market)?;
+    }
+    if let Some(deposit) = DEPOSITS.may_load(storage, poll_id.to_string())? {
+        DEPOSITS.remove(storage, poll_id.to_string());
+        messages.push(BankMsg::Send {
+            to_address: slash_to.unwrap_or(&deposit.depositor).to_string(),
+            amount: vec![deposit.amount],
+        });
+    }
+    Ok(messages)
+}
+
+// Chốt weight của tối đa `limit` voter (giống PruneBallots) để poll đông voter vẫn finalize được.
+// Trả về (đã chốt xong chưa, quỹ cần trả lại creator nếu không ai vote)
+fn settle_reward_pool(
+    storage: &mut dyn Storage,
+    poll_id: &str,
+    poll: &Poll,
+    limit: u32,
+) -> StdResult<(bool, Option<Coin>)> {
+    let mut pool = match REWARD_POOLS.may_load(storage, poll_id.to_string())? {
+        Some(pool) if !pool.settled => pool,
+        _ => return Ok((true, None)),
+    };
+
+    let start = pool.settle_cursor.clone().map(Bound::exclusive);
+    let voters = POLL_VOTERS
+        .prefix(poll_id.to_string())
+        .keys(storage, start, None, Order::Ascending)
+        .take(limit as usize)
+        .collect::<StdResult<Vec<_>>>()?;
+    for voter in &voters {
+        let weight = match pool.split {
+            RewardSplit::Equal => 1,
+            RewardSplit::Weighted => 1 + count_delegated(storage, poll_id, poll, voter)?,
+        };
+        pool.total_weight += weight;
+        VOTER_WEIGHTS.save(storage, (poll_id.to_string(), voter.clone()), &weight)?;
+    }
+    if let Some(last) = voters.last() {
+        pool.settle_cursor = Some(last.clone());
+    }
+    pool.settled = voters.len() < limit as usize;
+
+    // không ai vote -> trả lại toàn bộ quỹ
+    if pool.settled && pool.total_weight == 0 {
+        REWARD_POOLS.remove(storage, poll_id.to_string());
+        return Ok((true, Some(pool.funds)));
+    }
+    REWARD_POOLS.save(storage, poll_id.to_string(), &pool)?;
+    Ok((pool.settled, None))
+}
+
+// Phần thưởng của voter theo weight đã chốt
+fn reward_share(pool: &RewardPool, weight: u64) -> Uint128 {
+    pool.funds.amount.multiply_ratio(weight, pool.total_weight)
+}
+
+fn execute_delete_poll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let poll = load_poll(deps.storage, &poll_id)?;
+    if info.sender != poll.creator && !has_role(deps.storage, &config, &info.sender, Role::Owner)? {
+        return Err(ContractError::Unauthorized {});
+    }
+    if poll.status == PollStatus::Open {
+        return Err(ContractError::PollNotEnded {});
+    }
+    // ballot còn cần để chốt weight của quỹ thưởng
+    if let Some(pool) = REWARD_POOLS.may_load(deps.storage, poll_id.clone())? {
+        if !pool.settled {
+            return Err(ContractError::RewardsNotSettled {});
+        }
+    }
+
+    unindex_poll(deps.storage, &poll_id, &poll);
+    CREATED_POLLS.remove(deps.storage, (poll.creator.clone(), poll_id.clone()));
+    // lấy từ TALLIES đã lưu, không duyệt lại toàn bộ voter để tính phiếu uỷ quyền
+    let options = load_tally(deps.storage, &poll_id)?;
+    POLL_RESULTS.save(
+        deps.storage,
+        poll_id.clone(),
+        &PollResult {
+            creator: poll.creator,
+            question: poll.question,
+            options,
+            status: poll.status,
+            deleted_height: env.block.height,
+        },
+    )?;
+    POLLS.remove(deps.storage, poll_id.clone());
+    for index in 0..poll.option_count {
+        OPTIONS.remove(deps.storage, (poll_id.clone(), index));
+        TALLIES.remove(deps.storage, (poll_id.clone(), index), env.block.height)?;
+    }
+    let mut stats = STATS.may_load(deps.storage)?.unwrap_or_default();
+    stats.top_polls.retain(|(id, _)| *id != poll_id);
+    STATS.save(deps.storage, &stats)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "delete_poll")
+        .add_attribute("poll_id", poll_id))
+}
+
+// Ai cũng gọi được, xoá theo lô để poll lớn không vượt gas
+fn execute_prune_ballots(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    poll_id: String,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    if POLLS.has(deps.storage, poll_id.clone()) {
+        return Err(ContractError::PollNotDeleted {});
+    }
+    let result = POLL_RESULTS
+        .may_load(deps.storage, poll_id.clone())?
+        .ok_or(ContractError::PollNotDeleted {})?;
+    // market cần ballot và stake để tính payout, chỉ prune khi mọi người đã claim
+    let market = MARKETS.may_load(deps.storage, poll_id.clone())?;
+    if let Some(market) = &market {
+        let claimable = match market.status {
+            MarketStatus::Open | MarketStatus::Voided => market.stakes.iter().sum(),
+            MarketStatus::Resolved { winning_stake, .. } => winning_stake,
+        };
+        if market.claimed_stake < claimable {
+            return Err(ContractError::PayoutsPending {});
+        }
+    }
+    // weight đã chốt thì không cần ballot nữa, nhưng phải giữ tới khi mọi voter nhận thưởng
+    let rewards_claimed = match REWARD_POOLS.may_load(deps.storage, poll_id.clone())? {
+        Some(pool) => pool.claimed_weight == pool.total_weight,
+        None => true,
+    };
+    let limit = limit.unwrap_or(DEFAULT_PRUNE_LIMIT).min(MAX_PRUNE_LIMIT) as usize;
+    if limit == 0 {
+        return Err(ContractError::InvalidLimit {});
+    }
+
+    let voters = POLL_VOTERS
+        .prefix(poll_id.clone())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    for voter in &voters {
+        BALLOTS.remove(deps.storage, (voter.clone(), poll_id.clone()));
+        POLL_VOTERS.remove(deps.storage, (poll_id.clone(), voter.clone()));
+    }
+    let mut pruned = voters.len();
+
+    pruned += prune_poll_map(deps.storage, &COMMITMENTS, &poll_id, limit - pruned)?;
+    pruned += prune_poll_map(deps.storage, &POLL_REVISIONS, &poll_id, limit - pruned)?;
+    pruned += prune_poll_map(deps.storage, &REPORTS, &poll_id, limit - pruned)?;
+    pruned += prune_poll_map(deps.storage, &TURNOUT, &poll_id, limit - pruned)?;
+    for index in 0..result.options.len() as u32 {
+        pruned += prune_poll_map(
+            deps.storage,
+            TALLIES.changelog(),
+            &(poll_id.clone(), index),
+            limit - pruned,
+        )?;
+    }
+
+    let delegators = POLL_DELEGATIONS
+        .prefix(poll_id.clone())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .take(limit - pruned)
+        .collect::<StdResult<Vec<_>>>()?;
+    for delegator in &delegators {
+        let key = (delegator.clone(), poll_id.clone());
+        if let Some(delegate) = DELEGATIONS.may_load(deps.storage, key.clone())? {
+            DELEGATORS.remove(deps.storage, (delegate, poll_id.clone(), delegator.clone()));
+        }
+        DELEGATIONS.remove(deps.storage, key);
+        POLL_DELEGATIONS.remove(deps.storage, (poll_id.clone(), delegator.clone()));
+    }
+    pruned += delegators.len();
+
+    pruned += prune_poll_map(deps.storage, &STAKES, &poll_id, limit - pruned)?;
+    pruned += prune_poll_map(deps.storage, &PAYOUT_CLAIMED, &poll_id, limit - pruned)?;
+    if rewards_claimed {
+        pruned += prune_poll_map(deps.storage, &VOTER_WEIGHTS, &poll_id, limit - pruned)?;
+        pruned += prune_poll_map(deps.storage, &REWARD_CLAIMED, &poll_id, limit - pruned)?;
+    }
+
+    let done = pruned < limit && rewards_claimed;
+    if done {
+        MARKETS.remove(deps.storage, poll_id.clone());
+        REWARD_POOLS.remove(deps.storage, poll_id.clone());
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "prune_ballots")
+        .add_attribute("poll_id", poll_id)
+        .add_attribute("pruned", pruned.to_string())
+        .add_attribute("done", done.to_string()))
+}
+
+// Xoá tối đa `limit` record có key bắt đầu bằng `prefix`, trả về số record đã xoá
+fn prune_poll_map<P, K, T>(
+    storage: &mut dyn Storage,
+    map: &Map<(P, K), T>,
+    prefix: &P,
+    limit: usize,
+) -> StdResult<usize>
+where
+    P: PrimaryKey<'static> + Prefixer<'static> + KeyDeserialize + Clone,
+    K: PrimaryKey<'static> + KeyDeserialize<Output = K> + Clone + 'static,
+    T: Serialize + DeserializeOwned,
+{
+    let keys = map
+        .prefix(prefix.clone())
+        .keys(storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    for key in &keys {
+        map.remove(storage, (prefix.clone(), key.clone()));
+    }
+    Ok(keys.len())
+}
+
+// Ai cũng gọi được, chốt tiếp weight của quỹ thưởng theo lô
+fn execute_settle_rewards(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    poll_id: String,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let poll = load_poll(deps.storage, &poll_id)?;
+    if poll.status != PollStatus::Finalized {
+        return Err(ContractError::PollNotEnded {});
+    }
+    if !REWARD_POOLS.has(deps.storage, poll_id.clone()) {
+        return Err(ContractError::NoReward {});
+    }
+    let limit = limit.unwrap_or(DEFAULT_SETTLE_LIMIT).min(MAX_SETTLE_LIMIT);
+    if limit == 0 {
+        return Err(ContractError::InvalidLimit {});
+    }
+
+    let (done, refund) = settle_reward_pool(deps.storage, &poll_id, &poll, limit)?;
+    let mut res = Response::new()
+        .add_attribute("action", "settle_rewards")
+        .add_attribute("poll_id", poll_id)
+        .add_attribute("done", done.to_string());
+    if let Some(refund) = refund {
+        res = res.add_message(BankMsg::Send {
+            to_address: poll.creator.to_string(),
+            amount: vec![refund],
+        });
+    }
+    Ok(res)
+}
+
+fn execute_claim_reward(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    poll_id: String,
+) -> Result<Response, ContractError> {
+    // poll đã bị xoá vẫn claim được qua kết quả gọn
+    let (status, creator) = match POLLS.may_load(deps.storage, poll_id.clone())? {
+        Some(poll) => (poll.status, poll.creator),
+        None => {
+            let result = POLL_RESULTS.load(deps.storage, poll_id.clone())?;
+            (result.status, result.creator)
+        }
+    };
+    if status != PollStatus::Finalized {
+        return Err(ContractError::PollNotEnded {});
+    }
+    let mut pool = REWARD_POOLS
+        .may_load(deps.storage, poll_id.clone())?
+        .ok_or(ContractError::NoReward {})?;
+    if !pool.settled {
+        return Err(ContractError::RewardsNotSettled {});
+    }
+    let key = (poll_id.clone(), info.sender.clone());
+    let weight = VOTER_WEIGHTS
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::NoReward {})?;
+    if REWARD_CLAIMED.has(deps.storage, key.clone()) {
+        return Err(ContractError::AlreadyClaimed {});
+    }
+    REWARD_CLAIMED.save(deps.storage, key, &true)?;
+
+    let amount = reward_share(&pool, weight);
+    pool.claimed_weight += weight;
+    pool.paid += amount;
+    REWARD_POOLS.save(deps.storage, poll_id.clone(), &pool)?;
+
+    let mut res = Response::new()
+        .add_attribute("action", "claim_reward")
+        .add_attribute("poll_id", poll_id)
+        .add_attribute("amount", amount);
+    if !amount.is_zero() {
+        res = res.add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![coin(amount.u128(), pool.funds.denom.clone())],
+        });
+    }
+    // voter cuối cùng đã nhận -> phần lẻ không chia được trả lại creator
+    let dust = pool.funds.amount - pool.paid;
+    if pool.claimed_weight == pool.total_weight && !dust.is_zero() {
+        res = res.add_message(BankMsg::Send {
+            to_address: creator.to_string(),
+            amount: vec![coin(dust.u128(), pool.funds.denom)],
+        });
+    }
+    Ok(res)
+}
+
+fn execute_resolve_poll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: String,
+    outcome: String,
+) -> Result<Response, ContractError> {
+    let mut market = MARKETS.load(deps.storage, poll_id.clone())?;
+    if info.sender != market.resolver {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut poll = load_poll(deps.storage, &poll_id)?;
+    if market.status != MarketStatus::Open || poll.status != PollStatus::Open {
+        return Err(ContractError::PollNotOpen {});
+    }
+    // chỉ báo kết quả khi market đã ngừng nhận stake
+    if env.block.height < market.close_height {
+        return Err(ContractError::PollNotEnded {});
+    }
+    let position = option_index(deps.storage, &poll_id, &outcome)
+        .map_err(|_| ContractError::InvalidOutcome {})?;
+
+    let total: Uint128 = market.stakes.iter().sum();
+    let winning_stake = market.stakes[position as usize];
+    let mut fee = Uint128::zero();
+    if winning_stake.is_zero() {
+        // không ai đoán đúng -> hoàn stake cho tất cả
+        market.status = MarketStatus::Voided;
+    } else {
+        let losing_stake = total - winning_stake;
+        fee = losing_stake.mul_floor(CONFIG.load(deps.storage)?.market_fee);
+        market.status = MarketStatus::Resolved {
+            winner: position,
+            winning_stake,
+            pot: losing_stake - fee,
+        };
+    }
+    MARKETS.save(deps.storage, poll_id.clone(), &market)?;
+
+    let mut messages = finalize_poll(deps.storage, env.block.height, &poll_id, &mut poll)?;
+    if !fee.is_zero() {
+        // phí giao thức gửi cho admin
+        messages.push(BankMsg::Send {
+            to_address: CONFIG.load(deps.storage)?.admin.to_string(),
+            amount: vec![coin(fee.u128(), market.denom)],
+        });
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "resolve_poll")
+        .add_attribute("poll_id", poll_id)
+        .add_attribute("outcome", outcome)
+        .add_attribute("fee", fee)
+        .add_messages(messages))
+}
+
+fn execute_void_poll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    poll_id: String,
+) -> Result<Response, ContractError> {
+    let market = MARKETS.load(deps.storage, poll_id.clone())?;
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != market.resolver
+        && !has_role(deps.storage, &config, &info.sender, Role::Owner)?
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut poll = load_poll(deps.storage, &poll_id)?;
+    if market.status != MarketStatus::Open || poll.status != PollStatus::Open {
+        return Err(ContractError::PollNotOpen {});
+    }
+
+    // không phải spam nên tiền cọc trả lại creator
+    let messages = cancel_poll(deps.storage, env.block.height, &poll_id, &mut poll, None)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "void_poll")
+        .add_attribute("poll_id", poll_id)
+        .add_messages(messages))
+}
+
+// (stake, số tiền nhận được) của voter trong market
+fn market_payout(
+    storage: &dyn Storage,
+    poll_id: &str,
+    market: &Market,
+    voter: &Addr,
+) -> StdResult<(Uint128, Uint128)> {
+    let stake = STAKES
+        .may_load(storage, (poll_id.to_string(), voter.clone()))?
+        .unwrap_or_default();
+    let payout = match &market.status {
+        MarketStatus::Open => Uint128::zero(),
+        MarketStatus::Voided => stake,
+        MarketStatus::Resolved {
+            winner,
+            winning_stake,
+            pot,
+        } => match BALLOTS.may_load(storage, (voter.clone(), poll_id.to_string()))? {
+            Some(ballot) if ballot.option == *winner && !stake.is_zero() => {
+                stake + pot.multiply_ratio(stake, *winning_stake)
+            }
+            _ => Uint128::zero(),
+        },
+    };
+    Ok((stake, payout))
+}
+
+fn execute_claim_payout(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    poll_id: String,
+) -> Result<Response, ContractError> {
+    let mut market = MARKETS.load(deps.storage, poll_id.clone())?;
+    if market.status == MarketStatus::Open {
+        return Err(ContractError::PollNotEnded {});
+    }
+    let key = (poll_id.clone(), info.sender.clone());
+    if PAYOUT_CLAIMED.has(deps.storage, key.clone()) {
+        return Err(ContractError::AlreadyClaimed {});
+    }
+    let (stake, payout) = market_payout(deps.storage, &poll_id, &market, &info.sender)?;
+    if payout.is_zero() {
+        return Err(ContractError::NoPayout {});
+    }
+    PAYOUT_CLAIMED.save(deps.storage, key, &true)?;
+    market.claimed_stake += stake;
+    market.paid += payout;
+    MARKETS.save(deps.storage, poll_id.clone(), &market)?;
+
+    let mut res = Response::new()
+        .add_attribute("action", "claim_payout")
+        .add_attribute("poll_id", poll_id)
+        .add_attribute("amount", payout)
+        .add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: vec![coin(payout.u128(), market.denom.clone())],
+        });
+    // người thắng cuối cùng đã claim -> phần lẻ do làm tròn gửi cho admin như phí
+    if let MarketStatus::Resolved {
+        winning_stake, pot, ..
+    } = market.status
+    {
+        let dust = winning_stake + pot - market.paid;
+        if market.claimed_stake == winning_stake && !dust.is_zero() {
+            res = res.add_message(BankMsg::Send {
+                to_address: CONFIG.load(deps.storage)?.admin.to_string(),
+                amount: vec![coin(dust.u128(), market.denom)],
+            });
+        }
+    }
+    Ok(res)
+}
+
+fn execute_pause(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    scopes: Option<Vec<PauseScope>>,
+    until_height: Option<u64>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if !has_role(deps.storage, &config, &info.sender, Role::Owner)? {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let scopes = scopes.unwrap_or_else(|| {
+        vec![
+            PauseScope::PollCreation,
+            PauseScope::Voting,
+            PauseScope::Claims,
+        ]
+    });
+    config.paused = Some(PauseState {
+        scopes,
+        until_height,
+    });
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attribute("action", "pause"))
+}
+
+fn execute_unpause(deps: DepsMut, _env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if !has_role(deps.storage, &config, &info.sender, Role::Owner)? {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    config.paused = None;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attribute("action", "unpause"))
+}
+
+fn execute_cancel_poll(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    poll_id: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !has_role(deps.storage, &config, &info.sender, Role::Moderator)? {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut poll = load_poll(deps.storage, &poll_id)?;
+    if poll.status != PollStatus::Open {
+        return Err(ContractError::PollNotOpen {});
+    }
+
+    // poll spam -> tiền cọc bị slash về community pool
+    let messages = cancel_poll(
+        deps.storage,
+        _env.block.height,
+        &poll_id,
+        &mut poll,
+        Some(&config.community_pool),
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel_poll")
+        .add_attribute("poll_id", poll_id)
+        .add_messages(messages))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn execute_update_config(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    admin: Option<String>,
+    poll_deposit: Option<Coin>,
+    community_pool: Option<String>,
+    market_fee: Option<Decimal>,
+    restricted_creation: Option<bool>,
+    report_threshold: Option<u32>,
+    categories: Option<Vec<String>>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if !has_role(deps.storage, &config, &info.sender, Role::Owner)? {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if let Some(admin) = admin {
+        config.admin = deps.api.addr_validate(&admin)?;
+    }
+    if let Some(poll_deposit) = poll_deposit {
+        config.poll_deposit = Some(poll_deposit).filter(|coin| !coin.amount.is_zero());
+    }
+    if let Some(community_pool) = community_pool {
+        config.community_pool = deps.api.addr_validate(&community_pool)?;
+    }
+    if let Some(market_fee) = market_fee {
+        if market_fee > Decimal::one() {
+            return Err(ContractError::InvalidFee {});
+        }
+        config.market_fee = market_fee;
+    }
+    if let Some(restricted_creation) = restricted_creation {
+        config.restricted_creation = restricted_creation;
+    }
+    if let Some(report_threshold) = report_threshold {
+        config.report_threshold = Some(report_threshold).filter(|threshold| *threshold > 0);
+    }
+    if let Some(categories) = categories {
+        config.categories = categories;
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attribute("action", "update_config"))
+}
+
+fn execute_set_hidden(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    poll_id: String,
+    hidden: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !has_role(deps.storage, &config, &info.sender, Role::Moderator)? {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut poll = load_poll(deps.storage, &poll_id)?;
+    poll.hidden = hidden;
+    // bỏ ẩn thì đếm report lại từ đầu, nếu không một report mới là poll bị ẩn lại ngay
+    if !hidden {
+        poll.reports = 0;
+    }
+    POLLS.save(deps.storage, poll_id.clone(), &poll)?;
+
+    let action = if hidden { "hide_poll" } else { "unhide_poll" };
+    Ok(Response::new()
+        .add_attribute("action", action)
+        .add_attribute("poll_id", poll_id))
+}
+
+fn execute_report_poll(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    poll_id: String,
+    reason: String,
+) -> Result<Response, ContractError> {
+    if reason.len() > MAX_REPORT_REASON_LEN {
+        return Err(ContractError::ReasonTooLong {});
+    }
+    let mut poll = load_poll(deps.storage, &poll_id)?;
+    let key = (poll_id.clone(), info.sender.clone());
+    if REPORTS.has(deps.storage, key.clone()) {
+        return Err(ContractError::AlreadyReported {});
+    }
+    REPORTS.save(deps.storage, key, &reason)?;
+
+    poll.reports += 1;
+    let config = CONFIG.load(deps.storage)?;
+    if matches!(config.report_threshold, Some(threshold) if poll.reports >= threshold) {
+        poll.hidden = true;
+    }
+    POLLS.save(deps.storage, poll_id.clone(), &poll)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "report_poll")
+        .add_attribute("poll_id", poll_id)
+        .add_attribute("reports", poll.reports.to_string())
+        .add_attribute("hidden", poll.hidden.to_string()))
+}
+
+// admin trong Config và Owner có mọi role
+fn has_role(storage: &dyn Storage, config: &Config, addr: &Addr, role: Role) -> StdResult<bool> {
+    if *addr == config.admin {
+        return Ok(true);
+    }
+    let roles = ROLES.may_load(storage, addr.clone())?.unwrap_or_default();
+    Ok(roles.contains(&Role::Owner) || roles.contains(&role))
+}
+
+fn execute_grant_role(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    address: String,
+    role: Role,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !has_role(deps.storage, &config, &info.sender, Role::Owner)? {
+        return Err(ContractError::Unauthorized {});
+    }
+    let addr = deps.api.addr_validate(&address)?;
+    let mut roles = ROLES
+        .may_load(deps.storage, addr.clone())?
+        .unwrap_or_default();
+    if !roles.contains(&role) {
+        roles.push(role);
+    }
+    ROLES.save(deps.storage, addr, &roles)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "grant_role")
+        .add_attribute("address", address))
+}
+
+fn execute_revoke_role(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    address: String,
+    role: Role,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !has_role(deps.storage, &config, &info.sender, Role::Owner)? {
+        return Err(ContractError::Unauthorized {});
+    }
+    let addr = deps.api.addr_validate(&address)?;
+    let mut roles = ROLES
+        .may_load(deps.storage, addr.clone())?
+        .unwrap_or_default();
+    roles.retain(|r| *r != role);
+    if roles.is_empty() {
+        ROLES.remove(deps.storage, addr);
+    } else {
+        ROLES.save(deps.storage, addr, &roles)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "revoke_role")
+        .add_attribute("address", address))
+}
+
+fn execute_update_electorate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !has_role(deps.storage, &config, &info.sender, Role::ElectorateManager)? {
+        return Err(ContractError::Unauthorized {});
+    }
+    for address in add {
+        let addr = deps.api.addr_validate(&address)?;
+        ELECTORATE.save(deps.storage, addr, &())?;
+    }
+    for address in remove {
+        let addr = deps.api.addr_validate(&address)?;
+        ELECTORATE.remove(deps.storage, addr);
+    }
+
+    Ok(Response::new().add_attribute("action", "update_electorate"))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(_deps: Deps, _env: Env, _msg: QueryMsg) -> StdResult<Binary> {
+    match _msg {
+        QueryMsg::AllPoll { include_hidden } => {
+            query_all_polls(_deps, _env, include_hidden.unwrap_or(false))
+        }
+        QueryMsg::Poll { poll_id } => query_poll(_deps, _env, poll_id),
+        QueryMsg::Vote { poll_id, address } => query_vote(_deps, _env, address, poll_id),
+        QueryMsg::Commitments { poll_id } => query_commitments(_deps, _env, poll_id),
+        QueryMsg::Tally { poll_id } => query_tally(_deps, _env, poll_id),
+        QueryMsg::Results { poll_id } => query_results(_deps, _env, poll_id),
+        QueryMsg::ResultsAt {
+            poll_id,
+            height,
+            start_after,
+            limit,
+        } => query_results_at(_deps, _env, poll_id, height, start_after, limit),
+        QueryMsg::Delegation { address, poll_id } => {
+            query_delegation(_deps, _env, address, poll_id)
+        }
+        QueryMsg::Nonce { address } => query_nonce(_deps, _env, address),
+        QueryMsg::Config {} => to_json_binary(&CONFIG.load(_deps.storage)?),
+        QueryMsg::Stats {} => to_json_binary(&STATS.may_load(_deps.storage)?.unwrap_or_default()),
+        QueryMsg::UserOverview {
+            address,
+            created_after,
+            voted_after,
+            delegations_after,
+            limit,
+        } => query_user_overview(
+            _deps,
+            _env,
+            address,
+            created_after,
+            voted_after,
+            delegations_after,
+            limit,
+        ),
+        QueryMsg::Deposit { poll_id } => query_deposit(_deps, _env, poll_id),
+        QueryMsg::Deposits { start_after, limit } => {
+            query_deposits(_deps, _env, start_after, limit)
+        }
+        QueryMsg::Reward { poll_id, address } => query_reward(_deps, _env, poll_id, address),
+        QueryMsg::Market { poll_id } => {
+            let market = MARKETS.may_load(_deps.storage, poll_id)?;
+            to_json_binary(&MarketResponse { market })
+        }
+        QueryMsg::Payout { poll_id, address } => query_payout(_deps, _env, poll_id, address),
+        QueryMsg::PollsByTag {
+            tag,
+            start_after,
+            limit,
+            include_hidden,
+        } => query_polls_by_tag(
+            _deps,
+            _env,
+            tag,
+            start_after,
+            limit,
+            include_hidden.unwrap_or(false),
+        ),
+        QueryMsg::SearchPolls {
+            prefix,
+            start_after,
+            limit,
+            include_hidden,
+        } => query_search_polls(
+            _deps,
+            _env,
+            prefix,
+            start_after,
+            limit,
+            include_hidden.unwrap_or(false),
+        ),
+        QueryMsg::Series { series_id } => query_series(_deps, _env, series_id),
+        QueryMsg::SeriesHistory {
+            series_id,
+            start_after,
+            limit,
+        } => query_series_history(_deps, _env, series_id, start_after, limit),
+        QueryMsg::Template { template_id } => query_template(_deps, _env, template_id),
+        QueryMsg::Templates { start_after, limit } => {
+            query_templates(_deps, _env, start_after, limit)
+        }
+        QueryMsg::PollResult { poll_id } => query_poll_result(_deps, _env, poll_id),
+        QueryMsg::PollHistory {
+            poll_id,
+            start_after,
+            limit,
+        } => query_poll_history(_deps, _env, poll_id, start_after, limit),
+        QueryMsg::Reports {
+            poll_id,
+            start_after,
+            limit,
+        } => query_reports(_deps, _env, poll_id, start_after, limit),
+        QueryMsg::Roles { address } => query_roles(_deps, _env, address),
+        QueryMsg::RoleMembers {
+            role,
+            start_after,
+            limit,
+        } => query_role_members(_deps, _env, role, start_after, limit),
+        QueryMsg::Electorate { start_after, limit } => {
+            query_electorate(_deps, _env, start_after, limit)
+        }
+    }
+    //unimplemented!()
+}
+
+fn query_all_polls(deps: Deps, _env: Env, include_hidden: bool) -> StdResult<Binary> {
+    let polls = POLLS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|p| Ok(p?.1))
+        .filter(|p: &StdResult<Poll>| include_hidden || !matches!(p, Ok(poll) if poll.hidden))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    // đoạn trên tương đương
+    // let polls_iter = POLLS
+    //     .range(deps.storage, None, None, Order::Ascending)
+    //     .map(|p| Ok(p?.1));
+    // let polls: StdResult<Vec<_>> = polls_iter.collect(); // Chỉ thực thi tại đây
+    // -> chưa unwrap
+    // let polls: Vec<_> = polls_iter.collect()?; // Unwrap ra Vec, nếu có lỗi sẽ trả về lỗi ngay lập tức
+
+    // POLLS.range(...): Trả về một iterator chứa từng mục (key, value) trong storage.
+    // None, None: Không có giới hạn dưới và trên → lấy toàn bộ dữ liệu.
+    // Order::Ascending: Lấy dữ liệu theo thứ tự tăng dần của key.
+
+    // .map(|p| Ok(p?.1))
+    //  p là một Result<(Key, Value), StdError>. Do .range() khi dùng với storage trả về iterator chứa phần tử Result<(key, value), StdError>
+    //  p?: Nếu p là Err, thoát khỏi hàm và trả lỗi.
+    // .1: Lấy phần Value (bỏ qua Key)
+
+    // .collect::<StdResult<Vec<_>>>()?
+    // Mục tiêu: Chuyển iterator thành Vec<Value>, nhưng vẫn giữ lỗi nếu có. lưu các value vào Vec
+    // StdResult<Vec<_>>:
+    // Ok(Vec<Value>) nếu tất cả phần tử thành công.
+    // Err(StdError) nếu có lỗi.
+    // ?: Nếu có lỗi, dừng và trả lỗi ngay lập tức.
+
+    to_json_binary(&AllPollsResponse { polls })
+}
+
+fn query_poll(deps: Deps, _env: Env, poll_id: String) -> StdResult<Binary> {
+    let poll = POLLS.may_load(deps.storage, poll_id.clone())?;
+    let options = load_options(deps.storage, &poll_id)?
+        .into_iter()
+        .zip(load_tally(deps.storage, &poll_id)?)
+        .map(|(option, (_, tally))| (option, tally))
+        .collect();
+
+    to_json_binary(&PollResponse { poll, options })
+    //unimplemented!()
+}
+
+fn query_vote(deps: Deps, _env: Env, address: String, poll_id: String) -> StdResult<Binary> {
+    let validated_address = deps.api.addr_validate(&address)?;
+    let vote = BALLOTS.may_load(deps.storage, (validated_address, poll_id))?;
+    to_json_binary(&VoteResponse { vote })
+    //unimplemented!();
+}
+
+fn query_deposit(deps: Deps, _env: Env, poll_id: String) -> StdResult<Binary> {
+    let deposit = DEPOSITS.may_load(deps.storage, poll_id)?;
+    to_json_binary(&DepositResponse { deposit })
+}
+
+fn query_deposits(
+    deps: Deps,
+    _env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let deposits = DEPOSITS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    to_json_binary(&DepositsResponse { deposits })
+}
+
+fn query_reward(deps: Deps, _env: Env, poll_id: String, address: String) -> StdResult<Binary> {
+    let address = deps.api.addr_validate(&address)?;
+    let pool = REWARD_POOLS.may_load(deps.storage, poll_id.clone())?;
+    let key = (poll_id, address);
+    let claimed = REWARD_CLAIMED.has(deps.storage, key.clone());
+
+    let mut claimable = Uint128::zero();
+    if let (Some(pool), false) = (pool.as_ref().filter(|pool| pool.settled), claimed) {
+        if let Some(weight) = VOTER_WEIGHTS.may_load(deps.storage, key)? {
+            claimable = reward_share(pool, weight);
+        }
+    }
+
+    to_json_binary(&RewardResponse {
+        pool,
+        claimable,
+        claimed,
+    })
+}
+
+fn query_payout(deps: Deps, _env: Env, poll_id: String, address: String) -> StdResult<Binary> {
+    let address = deps.api.addr_validate(&address)?;
+    let market = MARKETS.load(deps.storage, poll_id.clone())?;
+    let claimed = PAYOUT_CLAIMED.has(deps.storage, (poll_id.clone(), address.clone()));
+    let (stake, payout) = market_payout(deps.storage, &poll_id, &market, &address)?;
+
+    to_json_binary(&PayoutResponse {
+        stake,
+        claimable: if claimed { Uint128::zero() } else { payout },
+        claimed,
+    })
+}
+
+fn query_user_overview(
+    deps: Deps,
+    env: Env,
+    address: String,
+    created_after: Option<String>,
+    voted_after: Option<String>,
+    delegations_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let address = deps.api.addr_validate(&address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let created_ids = CREATED_POLLS
+        .prefix(address.clone())
+        .keys(
+            deps.storage,
+            created_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut created = vec![];
+    let mut deposits = vec![];
+    for poll_id in created_ids {
+        if let Some(deposit) = DEPOSITS.may_load(deps.storage, poll_id.clone())? {
+            deposits.push((poll_id.clone(), deposit.amount));
+        }
+        let poll = POLLS.load(deps.storage, poll_id.clone())?;
+        created.push((poll_id, poll));
+    }
+
+    // BALLOTS có key (voter, poll_id) nên cũng là index các poll address đã vote
+    let ballots = BALLOTS
+        .prefix(address.clone())
+        .range(
+            deps.storage,
+            voted_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut voted = vec![];
+    let mut rewards = vec![];
+    let mut payouts = vec![];
+    for (poll_id, ballot) in ballots {
+        let key = (poll_id.clone(), address.clone());
+
+        if !REWARD_CLAIMED.has(deps.storage, key.clone()) {
+            let pool = REWARD_POOLS.may_load(deps.storage, poll_id.clone())?;
+            let weight = VOTER_WEIGHTS.may_load(deps.storage, key.clone())?;
+            if let (Some(pool), Some(weight)) = (pool.filter(|pool| pool.settled), weight) {
+                let amount = reward_share(&pool, weight);
+                if !amount.is_zero() {
+                    rewards.push((poll_id.clone(), coin(amount.u128(), pool.funds.denom)));
+                }
+            }
+        }
+        if !PAYOUT_CLAIMED.has(deps.storage, key) {
+            if let Some(market) = MARKETS.may_load(deps.storage, poll_id.clone())? {
+                let (_, payout) = market_payout(deps.storage, &poll_id, &market, &address)?;
+                if !payout.is_zero() {
+                    payouts.push((poll_id.clone(), coin(payout.u128(), market.denom)));
+                }
+            }
+        }
+        // poll đã xoá nhưng ballot chưa prune thì bỏ qua
+        if let Some(poll) = POLLS.may_load(deps.storage, poll_id.clone())? {
+            voted.push(VotedPoll {
+                status: poll_phase(&poll, env.block.height),
+                poll_id,
+                question: poll.question,
+                ballot,
+            });
+        }
+    }
+
+    let delegations = DELEGATIONS
+        .prefix(address)
+        .range(
+            deps.storage,
+            delegations_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    to_json_binary(&UserOverviewResponse {
+        created,
+        voted,
+        rewards,
+        payouts,
+        deposits,
+        delegations,
+    })
+}
+
+// Bỏ poll đã xoá (và poll bị ẩn nếu không include_hidden) trước khi cắt trang
+fn visible_poll(
+    item: StdResult<(String, Option<Poll>)>,
+    include_hidden: bool,
+) -> Option<StdResult<(String, Poll)>> {
+    match item {
+        Ok((poll_id, Some(poll))) if include_hidden || !poll.hidden => Some(Ok((poll_id, poll))),
+        Ok(_) => None,
+        Err(err) => Some(Err(err)),
+    }
+}
+
+fn query_polls_by_tag(
+    deps: Deps,
+    _env: Env,
+    tag: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+    include_hidden: bool,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let polls = TAG_INDEX
+        .prefix(normalize(&tag))
+        .keys(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .map(|poll_id| {
+            let poll_id = poll_id?;
+            let poll = POLLS.may_load(deps.storage, poll_id.clone())?;
+            Ok((poll_id, poll))
+        })
+        .filter_map(|item| visible_poll(item, include_hidden))
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    to_json_binary(&PollsResponse { polls })
+}
+
+fn query_search_polls(
+    deps: Deps,
+    _env: Env,
+    prefix: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+    include_hidden: bool,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let prefix = normalize(&prefix);
+    // tiếp tục sau key của poll cuối trang trước, poll đó đã bị xoá thì lấy question từ POLL_RESULTS
+    let question = match &start_after {
+        Some(poll_id) => match POLLS.may_load(deps.storage, poll_id.clone())? {
+            Some(poll) => Some(poll.question),
+            None => POLL_RESULTS
+                .may_load(deps.storage, poll_id.clone())?
+                .map(|result| result.question),
+        },
+        None => None,
+    };
+    let start = match (start_after, question) {
+        (Some(poll_id), Some(question)) => Bound::exclusive(question_key(&poll_id, &question)),
+        _ => Bound::inclusive(prefix.clone()),
+    };
+    let polls = QUESTION_INDEX
+        .range(deps.storage, Some(start), None, Order::Ascending)
+        .take_while(|item| match item {
+            Ok((key, _)) => key.starts_with(&prefix),
+            Err(_) => true,
+        })
+        .map(|item| {
+            let (_, poll_id) = item?;
+            let poll = POLLS.may_load(deps.storage, poll_id.clone())?;
+            Ok((poll_id, poll))
+        })
+        .filter_map(|item| visible_poll(item, include_hidden))
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    to_json_binary(&PollsResponse { polls })
+}
+
+fn query_series(deps: Deps, _env: Env, series_id: String) -> StdResult<Binary> {
+    let series = SERIES.may_load(deps.storage, series_id)?;
+    to_json_binary(&SeriesResponse { series })
+}
+
+fn query_series_history(
+    deps: Deps,
+    _env: Env,
+    series_id: String,
+    start_after: Option<u32>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let instances = SERIES_HISTORY
+        .prefix(series_id)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| Ok(item?.1))
+        .collect::<StdResult<Vec<_>>>()?;
+    to_json_binary(&SeriesHistoryResponse { instances })
+}
+
+fn query_template(deps: Deps, _env: Env, template_id: String) -> StdResult<Binary> {
+    let template = TEMPLATES.may_load(deps.storage, template_id)?;
+    to_json_binary(&TemplateResponse { template })
+}
+
+fn query_templates(
+    deps: Deps,
+    _env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let templates = TEMPLATES
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    to_json_binary(&TemplatesResponse { templates })
+}
+
+fn query_poll_result(deps: Deps, _env: Env, poll_id: String) -> StdResult<Binary> {
+    let result = POLL_RESULTS.may_load(deps.storage, poll_id)?;
+    to_json_binary(&PollResultResponse { result })
+}
+
+fn query_poll_history(
+    deps: Deps,
+    _env: Env,
+    poll_id: String,
+    start_after: Option<u32>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let revisions = POLL_REVISIONS
+        .prefix(poll_id)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| Ok(item?.1))
+        .collect::<StdResult<Vec<_>>>()?;
+    to_json_binary(&PollHistoryResponse { revisions })
+}
+
+fn query_reports(
+    deps: Deps,
+    _env: Env,
+    poll_id: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?
+        .map(Bound::exclusive);
+    let reports = REPORTS
+        .prefix(poll_id)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    to_json_binary(&ReportsResponse { reports })
+}
+
+fn query_roles(deps: Deps, _env: Env, address: String) -> StdResult<Binary> {
+    let addr = deps.api.addr_validate(&address)?;
+    let roles = ROLES.may_load(deps.storage, addr)?.unwrap_or_default();
+    to_json_binary(&RolesResponse { roles })
+}
+
+fn query_role_members(
+    deps: Deps,
+    _env: Env,
+    role: Role,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?
+        .map(Bound::exclusive);
+    let members = ROLES
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| match item {
+            Ok((_, roles)) => roles.contains(&role),
+            Err(_) => true,
+        })
+        .take(limit)
+        .map(|item| Ok(item?.0))
+        .collect::<StdResult<Vec<_>>>()?;
+    to_json_binary(&RoleMembersResponse { members })
+}
+
+fn query_electorate(
+    deps: Deps,
+    _env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .map(|addr| deps.api.addr_validate(&addr))
+        .transpose()?
+        .map(Bound::exclusive);
+    let voters = ELECTORATE
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    to_json_binary(&ElectorateResponse { voters })
+}
+
+fn query_nonce(deps: Deps, _env: Env, address: String) -> StdResult<Binary> {
+    let address = deps.api.addr_validate(&address)?;
+    let nonce = NONCES.may_load(deps.storage, address)?.unwrap_or(0);
+
+    to_json_binary(&NonceResponse { nonce })
+}
+
+fn query_tally(deps: Deps, _env: Env, poll_id: String) -> StdResult<Binary> {
+    let poll = POLLS.load(deps.storage, poll_id.clone())?;
+    let tally = compute_tally(deps.storage, &poll_id, &poll)?;
+
+    to_json_binary(&TallyResponse {
+        options: tally.options,
+        delegated: tally.delegated,
+    })
+}
+
+fn query_results(deps: Deps, env: Env, poll_id: String) -> StdResult<Binary> {
+    let poll = POLLS.load(deps.storage, poll_id.clone())?;
+    let tally = compute_tally(deps.storage, &poll_id, &poll)?;
+    // voter trực tiếp + delegator có phiếu được tính, mỗi delegator chỉ tính một lần
+    let direct = POLL_VOTERS
+        .prefix(poll_id.clone())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .count() as u64;
+
+    to_json_binary(&build_results(
+        poll_id,
+        env.block.height,
+        poll_phase(&poll, env.block.height),
+        tally.options,
+        direct + tally.delegated,
+    ))
+}
+
+fn query_results_at(
+    deps: Deps,
+    _env: Env,
+    poll_id: String,
+    height: u64,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let poll = POLLS.load(deps.storage, poll_id.clone())?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    // changelog lưu giá trị trước mỗi block, cuối block `height` cũng là đầu block height + 1
+    let mut tally = vec![];
+    for (index, option) in load_options(deps.storage, &poll_id)?
+        .into_iter()
+        .enumerate()
+    {
+        let votes = TALLIES
+            .may_load_at_height(
+                deps.storage,
+                (poll_id.clone(), index as u32),
+                height.saturating_add(1),
+            )?
+            .unwrap_or_default();
+        tally.push((option.label, votes.u128() as u64));
+    }
+    let voters = TURNOUT
+        .prefix(poll_id.clone())
+        .range(
+            deps.storage,
+            None,
+            Some(Bound::inclusive(height)),
+            Order::Descending,
+        )
+        .next()
+        .transpose()?
+        .map(|(_, voters)| voters)
+        .unwrap_or(0);
+    let turnout = TURNOUT
+        .prefix(poll_id.clone())
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            Some(Bound::inclusive(height)),
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    to_json_binary(&ResultsAtResponse {
+        results: build_results(poll_id, height, poll_phase(&poll, height), tally, voters),
+        turnout,
+    })
+}
+
+// Trạng thái poll tại `height`, poll đã đóng trước khi có closed_height coi như luôn đóng
+fn poll_phase(poll: &Poll, height: u64) -> PollPhase {
+    let closed = !matches!(poll.closed_height, Some(closed) if height < closed);
+    match poll.status {
+        PollStatus::Finalized if closed => PollPhase::Finalized,
+        PollStatus::Cancelled if closed => PollPhase::Cancelled,
+        _ => match poll.voting_mode {
+            VotingMode::CommitReveal {
+                commit_end_height,
+                reveal_end_height,
+            } => {
+                if height <= commit_end_height {
+                    PollPhase::Voting
+                } else if height <= reveal_end_height {
+                    PollPhase::Revealing
+                } else {
+                    PollPhase::Ended
+                }
+            }
+            VotingMode::Public => match poll.end_height {
+                Some(end_height) if height > end_height => PollPhase::Ended,
+                _ => PollPhase::Voting,
+            },
+        },
+    }
+}
+
+// Tính phần trăm và người thắng từ số phiếu của từng option
+fn build_results(
+    poll_id: String,
+    height: u64,
+    status: PollPhase,
+    tally: Vec<(String, u64)>,
+    unique_voters: u64,
+) -> ResultsResponse {
+    let total_votes: u64 = tally.iter().map(|(_, votes)| votes).sum();
+    let max = tally.iter().map(|(_, votes)| *votes).max().unwrap_or(0);
+
+    let mut options = vec![];
+    let mut winners = vec![];
+    for (index, (label, votes)) in tally.into_iter().enumerate() {
+        if max > 0 && votes == max {
+            winners.push(index as u32);
+        }
+        let percentage = if total_votes == 0 {
+            Decimal::zero()
+        } else {
+            Decimal::from_ratio(votes * 100, total_votes)
+        };
+        options.push(OptionResult {
+            index: index as u32,
+            label,
+            votes,
+            percentage,
+        });
+    }
+
+    ResultsResponse {
+        poll_id,
+        height,
+        status,
+        options,
+        total_votes,
+        unique_voters,
+        tie: winners.len() > 1,
+        winners,
+    }
+}
+
+fn query_delegation(
+    deps: Deps,
+    _env: Env,
+    address: String,
+    poll_id: Option<String>,
+) -> StdResult<Binary> {
+    let address = deps.api.addr_validate(&address)?;
+    let delegate = delegate_of(deps.storage, &address, poll_id.as_deref())?;
+
+    let mut chain = vec![];
+    let mut current = delegate.clone();
+    while let Some(addr) = current {
+        if addr == address || chain.contains(&addr) {
+            break;
+        }
+        current = delegate_of(deps.storage, &addr, poll_id.as_deref())?;
+        chain.push(addr);
+    }
+
+    // người uỷ quyền global chỉ tính khi họ không có delegation riêng cho poll
+    let mut delegators = vec![];
+    for delegator in DELEGATORS
+        .prefix((address.clone(), GLOBAL_SCOPE.to_string()))
+        .keys(deps.storage, None, None, Order::Ascending)
+    {
+        let delegator = delegator?;
+        let overridden = match &poll_id {
+            Some(poll_id) => DELEGATIONS.has(deps.storage, (delegator.clone(), poll_id.clone())),
+            None => false,
+        };
+        if !overridden {
+            delegators.push(delegator);
+        }
+    }
+    if let Some(poll_id) = poll_id {
+        for delegator in
+            DELEGATORS
+                .prefix((address, poll_id))
+                .keys(deps.storage, None, None, Order::Ascending)
+        {
+            delegators.push(delegator?);
+        }
+    }
+
+    to_json_binary(&DelegationResponse {
+        delegate,
+        chain,
+        delegators,
+    })
+}
+
+fn query_commitments(deps: Deps, _env: Env, poll_id: String) -> StdResult<Binary> {
+    let mut committed = 0;
+    let mut revealed = 0;
+    let mut unrevealed = vec![];
+    for item in COMMITMENTS
+        .prefix(poll_id)
+        .range(deps.storage, None, None, Order::Ascending)
+    {
+        let (voter, commitment) = item?;
+        committed += 1;
+        if commitment.revealed {
+            revealed += 1;
+        } else {
+            unrevealed.push(voter);
+        }
+    }
+
+    to_json_binary(&CommitmentsResponse {
+        committed,
+        revealed,
+        unrevealed,
+    })
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    // chỉ migrate từ chính contract này và không cho hạ version
+    let stored = get_contract_version(deps.storage)?;
+    if stored.contract != CONTRACT_NAME
+        || parse_version(&stored.version) > parse_version(CONTRACT_VERSION)
+    {
+        return Err(ContractError::InvalidMigration {
+            contract: stored.contract,
+            version: stored.version,
+        });
+    }
+    // bản 0.1.x còn lưu options trong poll và label trong ballot
+    if parse_version(&stored.version) < parse_version("0.2.0") {
+        migrate_legacy_state(deps.storage, _env.block.height)?;
+    }
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", stored.version)
+        .add_attribute("to_version", CONTRACT_VERSION))
+}
+
+// Chuyển config, poll và ballot của bản 0.1.x sang layout hiện tại.
+// Duyệt toàn bộ poll / ballot cũ nên chỉ chạy một lần lúc migrate
+fn migrate_legacy_state(storage: &mut dyn Storage, height: u64) -> Result<(), ContractError> {
+    let legacy = LEGACY_CONFIG.load(storage)?;
+    CONFIG.save(
+        storage,
+        &Config {
+            admin: legacy.admin.clone(),
+            poll_deposit: None,
+            // bản cũ chưa có community pool, tiền cọc bị slash gửi về admin
+            community_pool: legacy.admin,
+            market_fee: Decimal::zero(),
+            paused: None,
+            restricted_creation: false,
+            report_threshold: None,
+            categories: vec![],
+        },
+    )?;
+
+    let polls = LEGACY_POLLS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<BTreeMap<_, _>>>()?;
+    let ballots = LEGACY_BALLOTS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut stats = STATS.may_load(storage)?.unwrap_or_default();
+
+    for (poll_id, legacy) in &polls {
+        let mut poll = Poll {
+            creator: legacy.creator.clone(),
+            question: legacy.question.clone(),
+            option_count: 0,
+            voting_mode: VotingMode::Public,
+            status: PollStatus::Open,
+            eligibility: Eligibility::Anyone,
+            hidden: false,
+            reports: 0,
+            metadata: PollMetadata::default(),
+            revision: 0,
+            end_height: None,
+            vote_change: VoteChangePolicy::default(),
+            closed_height: None,
+        };
+        let labels = legacy
+            .options
+            .iter()
+            .map(|(label, _)| label.clone())
+            .collect();
+        save_options(storage, height, poll_id, &mut poll, labels, vec![])?;
+        for (index, (_, votes)) in legacy.options.iter().enumerate() {
+            TALLIES.save(
+                storage,
+                (poll_id.clone(), index as u32),
+                &Uint128::from(*votes),
+                height,
+            )?;
+        }
+        save_revision(storage, poll_id, &poll, height)?;
+        index_poll(storage, poll_id, &poll)?;
+        CREATED_POLLS.save(storage, (poll.creator.clone(), poll_id.clone()), &())?;
+        POLLS.save(storage, poll_id.clone(), &poll)?;
+        stats.total_polls += 1;
+        stats.open_polls += 1;
+    }
+
+    for ((voter, poll_id), ballot) in ballots {
+        let legacy = polls
+            .get(&poll_id)
+            .ok_or_else(|| ContractError::PollNotFound {
+                poll_id: poll_id.clone(),
+            })?;
+        let option = legacy
+            .options
+            .iter()
+            .position(|(label, _)| *label == ballot.option)
+            .ok_or(ContractError::InvalidOption {
+                option: ballot.option,
+            })? as u32;
+        BALLOTS.save(
+            storage,
+            (voter.clone(), poll_id.clone()),
+            &Ballot { option },
+        )?;
+        POLL_VOTERS.save(storage, (poll_id.clone(), voter.clone()), &())?;
+        if !VOTERS.has(storage, voter.clone()) {
+            VOTERS.save(storage, voter, &())?;
+            stats.unique_voters += 1;
+        }
+        stats.total_ballots += 1;
+        let turnout = record_turnout(storage, &poll_id, height, true)?;
+        rank_poll(&mut stats, &poll_id, turnout);
+    }
+    STATS.save(storage, &stats)?;
+    Ok(())
+}
+
+// "1.2.3" -> [1, 2, 3] để so sánh version
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_schema::QueryResponses;
+    use cosmwasm_std::{attr, from_json, Addr, Decimal};
+    //module attr, helper mod
+    //tạo và sử dụng các thuộc tính(attributes)
+    // e.g. : ("action", "instantiate")
+
+    use cosmwasm_std::testing::{message_info, mock_dependencies, mock_env};
+    //các hàm giả lập (mock function to mock an envirionment, message info, dependencies)
+    //mock_dependencies tạo ra một đối tượng giả lập cho các Deps trong môi trường CosmWasm. Nó bao gồm các phần như bộ lưu trữ (storage), API, và querier mà hợp đồng sẽ sử dụng. Điều này giúp bạn kiểm tra các hành động như ghi dữ liệu vào bộ lưu trữ mà không cần một blockchain thật.
+    //mock_env tạo ra một đối tượng giả lập cho môi trường (Env) mà hợp đồng thông minh chạy trong đó. Nó bao gồm các thông tin như thời gian, địa chỉ của người gọi, và các yếu tố khác liên quan đến môi trường thực thi.
+    //mock_info giúp tạo ra thông tin giả lập cho MessageInfo, bao gồm địa chỉ người gọi và các tiền tệ gửi kèm (nếu có). Đây là đối tượng chứa các thông tin về người gửi giao dịch
+
+    use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{
+        coin, Api, BankMsg, Binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Uint128,
+    };
+    use cosmwasm_std::{Empty, Order, OwnedDeps, Record, StdResult, Storage};
+    use k256::ecdsa::{signature::Signer, Signature, SigningKey};
+    use std::cell::Cell;
+    use std::collections::BTreeMap;
+    use std::marker::PhantomData;
+
+    use crate::contract::{execute, instantiate, migrate, pubkey_to_canonical}; // hàm init của contract
+    use crate::helpers::{compute_commitment, signed_vote_sign_doc};
+    use crate::msg::{
+        AllPollsResponse, CommitmentsResponse, DelegationResponse, DepositResponse,
+        DepositsResponse, ElectorateResponse, ExecuteMsg, InstantiateMsg, MarketParams, MigrateMsg,
+        NonceResponse, PayoutResponse, PollHistoryResponse, PollPhase, PollResponse,
+        PollResultResponse, PollsResponse, QueryMsg, ReportsResponse, ResultsAtResponse,
+        ResultsResponse, RewardResponse, RoleMembersResponse, RolesResponse, SeriesHistoryResponse,
+        SeriesResponse, SignedVote, TallyResponse, TemplatesResponse, UserOverviewResponse,
+        VoteOption, VoteResponse,
+    };
+    use crate::state::{
+        Config, Deadline, Eligibility, LegacyBallot, LegacyConfig, LegacyPoll, OptionMetadata,
+        PauseScope, PollMetadata, PollStatus, RewardSplit, Role, Stats, Template,
+        TemplateVotingMode, VoteChangePolicy, VotingMode, GLOBAL_SCOPE, LEGACY_BALLOTS,
+        LEGACY_CONFIG, LEGACY_POLLS, MARKETS, STAKES,
+    };
+    use crate::ContractError;
+
+    use super::query; //
+
+    //các account giả lập
+    pub const ADDR1: &str = "cosmwasm14ch5q26mhx3jk5cxl88t278nper264ce5fa7agjr4cw0yfjj7c6q56drym";
+    pub const ADDR2: &str = "cosmwasm1cq2j7y4utseeatek2alfy5ttaphjrtdxqqz0sn820v9jupy0seuqmh8c9s";
+    pub const ADDR3: &str = "cosmwasm1384tqgn4nknw9dk7rt5u5axd5g6zwrsc4p8qed22t329h803205qhm564r";
+    pub const ADDR4: &str = "cosmwasm1q5nfz2u8guyfkjnyy2qw8kgdxeryae0jxuyaumze8ygqqxymrres6seka8";
+
+    #[test]
+    fn test_query_all_polls() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg);
+
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id_1".to_string(),
+            question: "jdksjkkdj".to_string(),
+            options: vec![
+                "Cosmos Hub".to_string(),
+                "Juno".to_string(),
+                "Osmosis".to_string(),
+            ],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id_2".to_string(),
+            question: "What's your colour?".to_string(),
+            options: vec!["Red".to_string(), "Green".to_string(), "Blue".to_string()],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let msg = QueryMsg::AllPoll {
+            include_hidden: None,
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: AllPollsResponse = from_json(&bin).unwrap();
+
+        assert_eq!(res.polls.len(), 2);
+    }
+
+    #[test]
+    fn test_query_poll() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Create a poll
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id_1".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec![
+                "Cosmos Hub".to_string(),
+                "Juno".to_string(),
+                "Osmosis".to_string(),
+            ],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Query for the poll that exists
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id_1".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResponse = from_json(&bin).unwrap();
+        // Expect a poll
+        assert!(res.poll.is_some());
+
+        // Query for the poll that does not exists
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id_not_exist".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResponse = from_json(&bin).unwrap();
+        // Expect none
+        assert!(res.poll.is_none());
+    }
+
+    // Previous code omitted
+    #[test]
+    fn test_query_vote() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+        // Instantiate the contract
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Create a poll
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id_1".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec![
+                "Cosmos Hub".to_string(),
+                "Juno".to_string(),
+                "Osmosis".to_string(),
+            ],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Create a vote
+        let msg = ExecuteMsg::Vote {
+            poll_id: "some_id_1".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Query for a vote that exists
+        let msg = QueryMsg::Vote {
+            poll_id: "some_id_1".to_string(),
+            address: ADDR1.to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: VoteResponse = from_json(&bin).unwrap();
+        // Expect the vote to exist
+        assert!(res.vote.is_some());
+
+        // Query for a vote that does not exists
+        let msg = QueryMsg::Vote {
+            poll_id: "some_id_2".to_string(),
+            address: ADDR2.to_string(),
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: VoteResponse = from_json(&bin).unwrap();
+        // Expect the vote to not exist
+        assert!(res.vote.is_none());
+    }
+    // Following code omitted
+
+    #[test]
+    fn test_instantiate() {
+        //eprintln!("{} ÂSSSASAS", Addr::unchecked(ADDR1));
+        let mut deps = mock_dependencies();
+        //Mock the dependencies
+
+        let env = mock_env();
+        //MOck the contract environment
+
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+        //Mock the message info
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        //Tạo message khi muốn đặt admin là người gọi
+
+        //eprintln!("hello1");
+        let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+        //deps.as_mut() -> lấy mutable reference của DepMut.
+        //.unwrap() -> giải nén kết quả kiểu Result -> sẽ panic nếu có lỗi
+
+        assert_eq!(
+            res.attributes,
+            vec![attr("action", "instantiate"), attr("admin", ADDR1)]
+        );
+        //trong Response của Cosmwasm có trường attributes là Vec<Attribute>
+    }
+
+    #[test]
+    fn test_instantiate_with_admin() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let msg = InstantiateMsg {
+            admin: Some(ADDR2.to_string()),
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+        assert_eq!(
+            res.attributes,
+            vec![attr("action", "instantiate"), attr("admin", ADDR2)],
+        );
+    }
+
+    #[test]
+    fn test_execute_create_poll_valid() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id".to_string(),
+            question: "What's your favourite cosmos coin?".to_string(),
+            options: vec![
+                "Cosmos hub".to_string(),
+                "Juno".to_string(),
+                "Osmosis".to_string(),
+            ],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+    }
+
+    #[test]
+    fn test_execute_create_poll_invalid() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id".to_string(),
+            question: "What's your favourite number?".to_string(),
+            options: vec![
+                "1".to_string(),
+                "2".to_string(),
+                "3".to_string(),
+                "4".to_string(),
+                "5".to_string(),
+                "6".to_string(),
+                "7".to_string(),
+                "8".to_string(),
+                "9".to_string(),
+                "10".to_string(),
+                "11".to_string(),
+            ],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+
+        let _err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        //unwrap_err(): lấy giá trị lỗi E từ Result<T,E> -> nếu kết quả trả về Ok(T) -> panic
+    }
+
+    #[test]
+    fn test_execute_vote_valid() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        //tạo poll
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec![
+                "Cosmos Hub".to_string(),
+                "Juno".to_string(),
+                "Osmosis".to_string(),
+            ],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        //vote lần đầu
+        let msg = ExecuteMsg::Vote {
+            poll_id: "some_id".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        //đổi vote
+        let msg = ExecuteMsg::Vote {
+            poll_id: "some_id".to_string(),
+            vote: VoteOption::Label("Osmosis".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+    }
+
+    #[test]
+    fn test_execute_vote_invalid() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::Vote {
+            // tạo vote nhưng poll_id chưa có
+            poll_id: "some_id".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+        };
+
+        //test error
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::PollNotFound { ref poll_id } if poll_id == "some_id"
+        ));
+
+        //tạo poll
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec![
+                "Cosmos Hub".to_string(),
+                "Juno".to_string(),
+                "Osmosis".to_string(),
+            ],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        //tạo vote không hợp lệ
+        let msg = ExecuteMsg::Vote {
+            poll_id: "some_id".to_string(),
+            vote: VoteOption::Label("DVPN".to_string()),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::InvalidOption { ref option } if option == "DVPN"
+        ));
+
+        // vote lại đúng option đã chọn: poll Changeable coi như không đổi gì
+        let msg = ExecuteMsg::Vote {
+            poll_id: "some_id".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::Vote {
+            poll_id: "some_id".to_string(),
+            vote: VoteOption::Index(1),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // tally không bị đếm hai lần
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id".to_string(),
+        };
+        let res: PollResponse = from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.options[1].1, 1);
+
+        // poll commit-reveal: reveal một option không tồn tại
+        create_commit_reveal_poll(deps.as_mut(), env.clone(), info.clone());
+        let msg = ExecuteMsg::CommitVote {
+            poll_id: "secret".to_string(),
+            commitment: compute_commitment(7, "salt", &info.sender),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let mut reveal_env = env.clone();
+        reveal_env.block.height += 11;
+        let msg = ExecuteMsg::RevealVote {
+            poll_id: "secret".to_string(),
+            vote: VoteOption::Label("DVPN".to_string()),
+            salt: "salt".to_string(),
+        };
+        let err = execute(deps.as_mut(), reveal_env, info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidOption { .. }));
+
+        // các execute khác trên poll không tồn tại
+        let msg = ExecuteMsg::FinalizePoll {
+            poll_id: "missing".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::PollNotFound { .. }));
+        let msg = ExecuteMsg::CommitVote {
+            poll_id: "missing".to_string(),
+            commitment: Binary::default(),
+        };
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::PollNotFound { .. }));
+    }
+
+    fn create_commit_reveal_poll(deps: DepsMut, env: Env, info: MessageInfo) {
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "secret".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: Some(VotingMode::CommitReveal {
+                commit_end_height: env.block.height + 10,
+                reveal_end_height: env.block.height + 20,
+            }),
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        execute(deps, env, info, msg).unwrap();
+    }
+
+    #[test]
+    fn test_execute_commit_reveal() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+        let info2 = message_info(&Addr::unchecked(ADDR2), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        create_commit_reveal_poll(deps.as_mut(), env.clone(), info.clone());
+
+        // vote công khai bị từ chối
+        let msg = ExecuteMsg::Vote {
+            poll_id: "secret".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::WrongVotingMode {}));
+
+        // commit phase: cả hai cùng commit
+        for (info, vote) in [(&info, 1), (&info2, 0)] {
+            let msg = ExecuteMsg::CommitVote {
+                poll_id: "secret".to_string(),
+                commitment: compute_commitment(vote, "salt", &info.sender),
+            };
+            execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        }
+
+        // chưa tới reveal phase
+        let msg = ExecuteMsg::RevealVote {
+            poll_id: "secret".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+            salt: "salt".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::NotInRevealPhase {}));
+
+        // ballot chưa công khai trong commit phase
+        let msg_query = QueryMsg::Vote {
+            poll_id: "secret".to_string(),
+            address: ADDR1.to_string(),
+        };
+        let res: VoteResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg_query.clone()).unwrap()).unwrap();
+        assert!(res.vote.is_none());
+
+        env.block.height += 11;
+        // commit thêm sau commit phase bị từ chối
+        let msg_commit = ExecuteMsg::CommitVote {
+            poll_id: "secret".to_string(),
+            commitment: compute_commitment(1, "salt", &info.sender),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg_commit).unwrap_err();
+        assert!(matches!(err, ContractError::CommitPhaseClosed {}));
+
+        // salt sai
+        let bad = ExecuteMsg::RevealVote {
+            poll_id: "secret".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+            salt: "wrong".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), bad).unwrap_err();
+        assert!(matches!(err, ContractError::CommitmentMismatch {}));
+
+        // ranh giới giữa salt và voter không thể dời được
+        assert_ne!(
+            compute_commitment(1, "saltA", &Addr::unchecked("B")),
+            compute_commitment(1, "salt", &Addr::unchecked("AB"))
+        );
+
+        execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::AlreadyRevealed {}));
+
+        let res: VoteResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg_query).unwrap()).unwrap();
+        assert_eq!(res.vote.unwrap().option, 1);
+
+        let msg = QueryMsg::Poll {
+            poll_id: "secret".to_string(),
+        };
+        let res: PollResponse = from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        let options: Vec<_> = res
+            .options
+            .iter()
+            .map(|(o, n)| (o.label.as_str(), *n))
+            .collect();
+        assert_eq!(options, vec![("Cosmos Hub", 0), ("Juno", 1)]);
+
+        // ADDR2 chưa reveal -> được báo riêng
+        let msg = QueryMsg::Commitments {
+            poll_id: "secret".to_string(),
+        };
+        let res: CommitmentsResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.committed, 2);
+        assert_eq!(res.revealed, 1);
+        assert_eq!(res.unrevealed, vec![Addr::unchecked(ADDR2)]);
+
+        // reveal theo index cũng khớp commitment
+        let msg = ExecuteMsg::RevealVote {
+            poll_id: "secret".to_string(),
+            vote: VoteOption::Index(0),
+            salt: "salt".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), info2.clone(), msg).unwrap();
+    }
+
+    #[test]
+    fn test_execute_commit_reveal_invalid() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // reveal phase phải nằm sau commit phase
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "secret".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: Some(VotingMode::CommitReveal {
+                commit_end_height: env.block.height + 10,
+                reveal_end_height: env.block.height + 10,
+            }),
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidPhases {}));
+
+        create_commit_reveal_poll(deps.as_mut(), env.clone(), info.clone());
+
+        // reveal khi chưa commit
+        env.block.height += 11;
+        let msg = ExecuteMsg::RevealVote {
+            poll_id: "secret".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+            salt: "salt".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::NoCommitment {}));
+
+        // reveal phase đã kết thúc
+        env.block.height += 10;
+        let msg = ExecuteMsg::RevealVote {
+            poll_id: "secret".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+            salt: "salt".to_string(),
+        };
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::NotInRevealPhase {}));
+    }
+
+    #[test]
+    fn test_execute_delegate() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info1 = message_info(&Addr::unchecked(ADDR1), &[]);
+        let info2 = message_info(&Addr::unchecked(ADDR2), &[]);
+        let info3 = message_info(&Addr::unchecked(ADDR3), &[]);
+        let info4 = message_info(&Addr::unchecked(ADDR4), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info1.clone(), msg).unwrap();
+
+        //tạo poll
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec![
+                "Cosmos Hub".to_string(),
+                "Juno".to_string(),
+                "Osmosis".to_string(),
+            ],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info1.clone(), msg).unwrap();
+
+        // ADDR3 -> ADDR2 -> ADDR1 (global), ADDR4 -> ADDR1 global nhưng -> ADDR2 riêng cho poll
+        for (info, to, poll_id) in [
+            (&info2, ADDR1, None),
+            (&info3, ADDR2, None),
+            (&info4, ADDR1, None),
+            (&info4, ADDR2, Some("some_id".to_string())),
+        ] {
+            let msg = ExecuteMsg::Delegate {
+                to: to.to_string(),
+                poll_id,
+            };
+            execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        }
+
+        // ADDR1 -> ADDR3 tạo vòng lặp
+        let msg = ExecuteMsg::Delegate {
+            to: ADDR3.to_string(),
+            poll_id: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info1.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::DelegationCycle {}));
+
+        let msg = ExecuteMsg::Vote {
+            poll_id: "some_id".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info1.clone(), msg).unwrap();
+
+        let tally = |deps: Deps| -> TallyResponse {
+            let msg = QueryMsg::Tally {
+                poll_id: "some_id".to_string(),
+            };
+            from_json(query(deps, mock_env(), msg).unwrap()).unwrap()
+        };
+        let res = tally(deps.as_ref());
+        assert_eq!(res.options[1], ("Juno".to_string(), 4));
+        assert_eq!(res.delegated, 3);
+
+        // ADDR2 tự vote -> override, ADDR3 và ADDR4 đi theo ADDR2
+        let msg = ExecuteMsg::Vote {
+            poll_id: "some_id".to_string(),
+            vote: VoteOption::Label("Osmosis".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info2.clone(), msg).unwrap();
+        let res = tally(deps.as_ref());
+        assert_eq!(res.options[1], ("Juno".to_string(), 1));
+        assert_eq!(res.options[2], ("Osmosis".to_string(), 3));
+        assert_eq!(res.delegated, 2);
+
+        let msg = QueryMsg::Delegation {
+            address: ADDR3.to_string(),
+            poll_id: Some("some_id".to_string()),
+        };
+        let res: DelegationResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.delegate, Some(Addr::unchecked(ADDR2)));
+        assert_eq!(
+            res.chain,
+            vec![Addr::unchecked(ADDR2), Addr::unchecked(ADDR1)]
+        );
+
+        // trong poll, ADDR4 uỷ quyền cho ADDR2 chứ không phải ADDR1
+        let msg = QueryMsg::Delegation {
+            address: ADDR1.to_string(),
+            poll_id: Some("some_id".to_string()),
+        };
+        let res: DelegationResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.delegate, None);
+        assert_eq!(res.delegators, vec![Addr::unchecked(ADDR2)]);
+
+        let msg = ExecuteMsg::Undelegate {
+            poll_id: Some("some_id".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info4.clone(), msg.clone()).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), info4.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::NoDelegation {}));
+
+        // ADDR4 quay về delegation global -> ADDR1
+        let res = tally(deps.as_ref());
+        assert_eq!(res.options[1], ("Juno".to_string(), 2));
+        assert_eq!(res.options[2], ("Osmosis".to_string(), 2));
+
+        // vòng lặp lẫn global và riêng poll: ADDR4 -> ADDR1 trong poll, rồi ADDR1 -> ADDR4 global
+        let msg = ExecuteMsg::Undelegate { poll_id: None };
+        execute(deps.as_mut(), env.clone(), info4.clone(), msg).unwrap();
+        let msg = ExecuteMsg::Delegate {
+            to: ADDR1.to_string(),
+            poll_id: Some("some_id".to_string()),
+        };
+        execute(deps.as_mut(), env.clone(), info4.clone(), msg).unwrap();
+        let msg = ExecuteMsg::Delegate {
+            to: ADDR4.to_string(),
+            poll_id: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info1.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::DelegationCycle {}));
+    }
+
+    fn sign_vote(
+        key: &SigningKey,
+        deps: Deps,
+        env: &Env,
+        poll_id: &str,
+        vote: &str,
+        nonce: u64,
+    ) -> SignedVote {
+        let pubkey = key
+            .verifying_key()
+            .to_encoded_point(true)
+            .as_bytes()
+            .to_vec();
+        let voter = deps
+            .api
+            .addr_humanize(&pubkey_to_canonical(&pubkey).unwrap())
+            .unwrap();
+        let sign_doc =
+            signed_vote_sign_doc(&env.contract.address, &voter, poll_id, vote, nonce).unwrap();
+        let signature: Signature = key.sign(&sign_doc);
+        SignedVote {
+            poll_id: poll_id.to_string(),
+            vote: vote.to_string(),
+            nonce,
+            pubkey: Binary::from(pubkey),
+            signature: Binary::from(signature.to_bytes().to_vec()),
+        }
+    }
+
+    #[test]
+    fn test_execute_submit_signed_votes() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        // relayer trả gas thay cho voter
+        let relayer = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), relayer.clone(), msg).unwrap();
+
+        //tạo poll
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec![
+                "Cosmos Hub".to_string(),
+                "Juno".to_string(),
+                "Osmosis".to_string(),
+            ],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), relayer.clone(), msg).unwrap();
+
+        let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let signed = sign_vote(&key, deps.as_ref(), &env, "some_id", "Juno", 0);
+        let voter = deps
+            .api
+            .addr_humanize(&pubkey_to_canonical(&signed.pubkey).unwrap())
+            .unwrap();
+
+        let msg = ExecuteMsg::SubmitSignedVotes {
+            votes: vec![signed],
+        };
+        let _res = execute(deps.as_mut(), env.clone(), relayer.clone(), msg.clone()).unwrap();
+
+        let query_msg = QueryMsg::Vote {
+            poll_id: "some_id".to_string(),
+            address: voter.to_string(),
+        };
+        let res: VoteResponse =
+            from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        assert_eq!(res.vote.unwrap().option, 1);
+
+        let query_msg = QueryMsg::Nonce {
+            address: voter.to_string(),
+        };
+        let res: NonceResponse =
+            from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        assert_eq!(res.nonce, 1);
+
+        // gửi lại cùng ballot -> replay bị chặn
+        let err = execute(deps.as_mut(), env.clone(), relayer.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidNonce { expected: 1 }));
+
+        // relayer sửa lựa chọn -> chữ ký không còn đúng
+        let mut tampered = sign_vote(&key, deps.as_ref(), &env, "some_id", "Juno", 1);
+        tampered.vote = "Osmosis".to_string();
+        let msg = ExecuteMsg::SubmitSignedVotes {
+            votes: vec![tampered],
+        };
+        let err = execute(deps.as_mut(), env.clone(), relayer.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSignature {}));
+
+        // pubkey không nén 65 byte bị từ chối trước khi tính address
+        let mut uncompressed = sign_vote(&key, deps.as_ref(), &env, "some_id", "Juno", 1);
+        uncompressed.pubkey = Binary::from(
+            key.verifying_key()
+                .to_encoded_point(false)
+                .as_bytes()
+                .to_vec(),
+        );
+        let err = pubkey_to_canonical(&uncompressed.pubkey).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSignature {}));
+        let msg = ExecuteMsg::SubmitSignedVotes {
+            votes: vec![uncompressed],
+        };
+        let err = execute(deps.as_mut(), env, relayer, msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSignature {}));
+    }
+
+    #[test]
+    fn test_execute_poll_deposit() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+        let deposit = coin(100, "ujuno");
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: Some(deposit.clone()),
+            community_pool: Some(ADDR3.to_string()),
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+
+        let create = |poll_id: &str| ExecuteMsg::CreatePoll {
+            poll_id: poll_id.to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+
+        // không gửi tiền cọc
+        let info = message_info(&Addr::unchecked(ADDR2), &[]);
+        let err = execute(deps.as_mut(), env.clone(), info, create("poll_1")).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidDeposit { .. }));
+
+        let info = message_info(&Addr::unchecked(ADDR2), &[coin(100, "ujuno")]);
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), create("poll_1")).unwrap();
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), create("poll_2")).unwrap();
+
+        // không ghi đè poll đã có
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), create("poll_1")).unwrap_err();
+        assert!(matches!(err, ContractError::PollAlreadyExists { .. }));
+
+        let msg = QueryMsg::Deposits {
+            start_after: None,
+            limit: None,
+        };
+        let res: DepositsResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.deposits.len(), 2);
+
+        // người lạ không finalize được
+        let msg = ExecuteMsg::FinalizePoll {
+            poll_id: "poll_1".to_string(),
+        };
+        let stranger = message_info(&Addr::unchecked(ADDR4), &[]);
+        let err = execute(deps.as_mut(), env.clone(), stranger, msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        // creator không được kết thúc sớm để lấy lại cọc
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::PollNotEnded {}));
+
+        // admin finalize -> trả cọc cho creator
+        let res = execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: ADDR2.to_string(),
+                amount: vec![deposit.clone()],
+            })
+        );
+
+        // poll đã kết thúc thì không vote được
+        let msg = ExecuteMsg::Vote {
+            poll_id: "poll_1".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+        };
+        let voter = message_info(&Addr::unchecked(ADDR2), &[]);
+        let err = execute(deps.as_mut(), env.clone(), voter, msg).unwrap_err();
+        assert!(matches!(err, ContractError::PollNotOpen {}));
+
+        // poll thường không nhận tiền gửi kèm vote
+        let msg = ExecuteMsg::Vote {
+            poll_id: "poll_2".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::UnexpectedFunds {}));
+
+        // chỉ admin được huỷ poll
+        let msg = ExecuteMsg::CancelPoll {
+            poll_id: "poll_2".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        // admin huỷ poll spam -> cọc về community pool
+        let res = execute(deps.as_mut(), env.clone(), admin, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: ADDR3.to_string(),
+                amount: vec![deposit],
+            })
+        );
+
+        let msg = QueryMsg::Deposit {
+            poll_id: "poll_2".to_string(),
+        };
+        let res: DepositResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert!(res.deposit.is_none());
+
+        // poll có duration: hết hạn thì creator tự finalize và lấy lại cọc
+        let info = message_info(&Addr::unchecked(ADDR2), &[coin(100, "ujuno")]);
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "poll_3".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: Some(10),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::FinalizePoll {
+            poll_id: "poll_3".to_string(),
+        };
+        let mut env = env;
+        env.block.height += 10;
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::PollNotEnded {}));
+
+        env.block.height += 1;
+        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: ADDR2.to_string(),
+                amount: vec![coin(100, "ujuno")],
+            })
+        );
+    }
+
+    #[test]
+    fn test_execute_claim_reward() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info1 = message_info(&Addr::unchecked(ADDR1), &[]);
+        let info2 = message_info(&Addr::unchecked(ADDR2), &[]);
+        let info3 = message_info(&Addr::unchecked(ADDR3), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info1.clone(), msg).unwrap();
+
+        // creator ADDR4 gửi kèm 100ucosm làm quỹ thưởng, chia theo weight
+        let creator = message_info(&Addr::unchecked(ADDR4), &[coin(100, "ucosm")]);
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: None,
+            reward_split: Some(RewardSplit::Weighted),
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), creator.clone(), msg).unwrap();
+
+        // không có quỹ thưởng cũng không có cọc thì không nhận tiền gửi kèm
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "no_pool".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), creator.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::UnexpectedFunds {}));
+
+        // ADDR3 uỷ quyền cho ADDR1 -> ADDR1 có weight 2
+        let msg = ExecuteMsg::Delegate {
+            to: ADDR1.to_string(),
+            poll_id: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info3.clone(), msg).unwrap();
+        for (info, vote) in [(&info1, "Juno"), (&info2, "Cosmos Hub")] {
+            let msg = ExecuteMsg::Vote {
+                poll_id: "some_id".to_string(),
+                vote: VoteOption::Label(vote.to_string()),
+            };
+            let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        }
+
+        // chưa finalize thì chưa nhận được
+        let claim = ExecuteMsg::ClaimReward {
+            poll_id: "some_id".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info1.clone(), claim.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::PollNotEnded {}));
+
+        // 100 * 2/3 = 66, 100 * 1/3 = 33 -> 1 ucosm lẻ trả lại creator khi voter cuối claim
+        let msg = ExecuteMsg::FinalizePoll {
+            poll_id: "some_id".to_string(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), creator, msg).unwrap();
+        assert!(res.messages.is_empty());
+
+        let res = execute(deps.as_mut(), env.clone(), info1.clone(), claim.clone()).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: ADDR1.to_string(),
+                amount: vec![coin(66, "ucosm")],
+            })
+        );
+        let err = execute(deps.as_mut(), env.clone(), info1, claim.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::AlreadyClaimed {}));
+
+        // ADDR3 không tự vote nên không có phần thưởng
+        let err = execute(deps.as_mut(), env.clone(), info3, claim.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::NoReward {}));
+
+        let msg = QueryMsg::Reward {
+            poll_id: "some_id".to_string(),
+            address: ADDR2.to_string(),
+        };
+        let res: RewardResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.claimable, Uint128::new(33));
+        assert!(!res.claimed);
+
+        let res = execute(deps.as_mut(), env, info2, claim).unwrap();
+        assert_eq!(
+            res.messages
+                .into_iter()
+                .map(|msg| msg.msg)
+                .collect::<Vec<_>>(),
+            vec![
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: ADDR2.to_string(),
+                    amount: vec![coin(33, "ucosm")],
+                }),
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: ADDR4.to_string(),
+                    amount: vec![coin(1, "ucosm")],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_execute_settle_rewards() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+
+        let creator = message_info(&Addr::unchecked(ADDR1), &[coin(1000, "ucosm")]);
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: None,
+            reward_split: Some(RewardSplit::Equal),
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), creator, msg).unwrap();
+
+        // nhiều voter hơn một lô chốt weight
+        let voters: Vec<_> = (0..super::DEFAULT_SETTLE_LIMIT + 10)
+            .map(|i| message_info(&Addr::unchecked(format!("voter{i:03}")), &[]))
+            .collect();
+        for info in &voters {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                info.clone(),
+                vote_index("some_id", 0),
+            )
+            .unwrap();
+        }
+
+        // finalize chỉ chốt lô đầu, chưa claim được
+        let msg = ExecuteMsg::FinalizePoll {
+            poll_id: "some_id".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+        let claim = ExecuteMsg::ClaimReward {
+            poll_id: "some_id".to_string(),
+        };
+        let err =
+            execute(deps.as_mut(), env.clone(), voters[0].clone(), claim.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::RewardsNotSettled {}));
+
+        let settle = |limit| ExecuteMsg::SettleRewards {
+            poll_id: "some_id".to_string(),
+            limit,
+        };
+        let err = execute(deps.as_mut(), env.clone(), admin.clone(), settle(Some(0))).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidLimit {}));
+
+        let res = execute(deps.as_mut(), env.clone(), admin.clone(), settle(Some(5))).unwrap();
+        assert_eq!(res.attributes[2], attr("done", "false"));
+        let res = execute(deps.as_mut(), env.clone(), admin, settle(None)).unwrap();
+        assert_eq!(res.attributes[2], attr("done", "true"));
+
+        // 1000 / 60 = 16 mỗi người
+        let res = execute(deps.as_mut(), env, voters[0].clone(), claim).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: voters[0].sender.to_string(),
+                amount: vec![coin(16, "ucosm")],
+            })
+        );
+    }
+
+    fn create_market_poll(deps: DepsMut, env: Env) {
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "market".to_string(),
+            question: "Which coin will flip ATOM first?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: None,
+            reward_split: None,
+            market: Some(MarketParams {
+                resolver: ADDR4.to_string(),
+                denom: "ucosm".to_string(),
+                close_height: env.block.height + 10,
+            }),
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+        execute(deps, env, info, msg).unwrap();
+    }
+
+    fn stake(deps: DepsMut, env: Env, sender: &str, vote: &str, amount: u128) {
+        let msg = ExecuteMsg::Vote {
+            poll_id: "market".to_string(),
+            vote: VoteOption::Label(vote.to_string()),
+        };
+        let info = message_info(&Addr::unchecked(sender), &[coin(amount, "ucosm")]);
+        execute(deps, env, info, msg).unwrap();
+    }
+
+    #[test]
+    fn test_execute_market_resolve() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+        // phí giao thức 10%
+        let msg = ExecuteMsg::UpdateConfig {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+            market_fee: Some(Decimal::percent(10)),
+            restricted_creation: None,
+            report_threshold: None,
+            categories: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+        create_market_poll(deps.as_mut(), env.clone());
+
+        stake(deps.as_mut(), env.clone(), ADDR2, "Juno", 60);
+        stake(deps.as_mut(), env.clone(), ADDR2, "Juno", 40);
+        stake(deps.as_mut(), env.clone(), ADDR3, "Cosmos Hub", 300);
+
+        // vote không kèm stake
+        let msg = ExecuteMsg::Vote {
+            poll_id: "market".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+        };
+        let err = execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidStake { .. }));
+
+        // đã stake thì không đổi option được
+        let msg = ExecuteMsg::Vote {
+            poll_id: "market".to_string(),
+            vote: VoteOption::Label("Cosmos Hub".to_string()),
+        };
+        let info = message_info(&Addr::unchecked(ADDR2), &[coin(10, "ucosm")]);
+        let err = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::StakeLocked {}));
+
+        // index ngoài khoảng trong market không được panic
+        let msg = ExecuteMsg::Vote {
+            poll_id: "market".to_string(),
+            vote: VoteOption::Index(7),
+        };
+        let info = message_info(&Addr::unchecked(ADDR4), &[coin(10, "ucosm")]);
+        let err = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::InvalidOptionIndex { index: 7, count: 2 }
+        ));
+
+        // chỉ resolver được báo kết quả
+        let msg = ExecuteMsg::ResolvePoll {
+            poll_id: "market".to_string(),
+            outcome: "Juno".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), admin, msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        // market còn nhận stake thì chưa resolve được
+        let resolver = message_info(&Addr::unchecked(ADDR4), &[]);
+        let err = execute(deps.as_mut(), env.clone(), resolver.clone(), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::PollNotEnded {}));
+
+        // tới close_height thì không stake thêm được
+        let mut env = env;
+        env.block.height += 10;
+        let vote = ExecuteMsg::Vote {
+            poll_id: "market".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+        };
+        let info = message_info(&Addr::unchecked(ADDR2), &[coin(10, "ucosm")]);
+        let err = execute(deps.as_mut(), env.clone(), info, vote).unwrap_err();
+        assert!(matches!(err, ContractError::PollNotOpen {}));
+
+        // bên thua 300 -> phí 30 cho admin, 270 chia cho bên thắng
+        let res = execute(deps.as_mut(), env.clone(), resolver, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: ADDR1.to_string(),
+                amount: vec![coin(30, "ucosm")],
+            })
+        );
+
+        let claim = ExecuteMsg::ClaimPayout {
+            poll_id: "market".to_string(),
+        };
+        let info = message_info(&Addr::unchecked(ADDR2), &[]);
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), claim.clone()).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: ADDR2.to_string(),
+                amount: vec![coin(370, "ucosm")],
+            })
+        );
+        let err = execute(deps.as_mut(), env.clone(), info, claim.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::AlreadyClaimed {}));
+
+        let info = message_info(&Addr::unchecked(ADDR3), &[]);
+        let err = execute(deps.as_mut(), env.clone(), info, claim).unwrap_err();
+        assert!(matches!(err, ContractError::NoPayout {}));
+
+        let msg = QueryMsg::Payout {
+            poll_id: "market".to_string(),
+            address: ADDR3.to_string(),
+        };
+        let res: PayoutResponse = from_json(query(deps.as_ref(), env, msg).unwrap()).unwrap();
+        assert_eq!(res.stake, Uint128::new(300));
+        assert_eq!(res.claimable, Uint128::zero());
+    }
+
+    #[test]
+    fn test_execute_market_dust() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), admin, msg).unwrap();
+        create_market_poll(deps.as_mut(), env.clone());
+
+        stake(deps.as_mut(), env.clone(), ADDR1, "Cosmos Hub", 10);
+        stake(deps.as_mut(), env.clone(), ADDR2, "Juno", 1);
+        stake(deps.as_mut(), env.clone(), ADDR3, "Juno", 2);
+
+        let mut env = env;
+        env.block.height += 10;
+        let msg = ExecuteMsg::ResolvePoll {
+            poll_id: "market".to_string(),
+            outcome: "Juno".to_string(),
+        };
+        let resolver = message_info(&Addr::unchecked(ADDR4), &[]);
+        let _res = execute(deps.as_mut(), env.clone(), resolver, msg).unwrap();
+
+        // xoá poll được nhưng chưa prune được khi còn payout chưa claim
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+        let msg = ExecuteMsg::DeletePoll {
+            poll_id: "market".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+        let prune = ExecuteMsg::PruneBallots {
+            poll_id: "market".to_string(),
+            limit: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), admin.clone(), prune.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::PayoutsPending {}));
+
+        // 1 + 10 * 1/3 = 4, 2 + 10 * 2/3 = 8 -> lẻ 1
+        let claim = ExecuteMsg::ClaimPayout {
+            poll_id: "market".to_string(),
+        };
+        let info = message_info(&Addr::unchecked(ADDR2), &[]);
+        let res = execute(deps.as_mut(), env.clone(), info, claim.clone()).unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        let info = message_info(&Addr::unchecked(ADDR3), &[]);
+        let res = execute(deps.as_mut(), env.clone(), info, claim).unwrap();
+        assert_eq!(
+            res.messages
+                .into_iter()
+                .map(|msg| msg.msg)
+                .collect::<Vec<_>>(),
+            vec![
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: ADDR3.to_string(),
+                    amount: vec![coin(8, "ucosm")],
+                }),
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: ADDR1.to_string(),
+                    amount: vec![coin(1, "ucosm")],
+                }),
+            ]
+        );
+
+        // mọi người thắng đã claim -> prune hết stake, ballot và market
+        let res = execute(deps.as_mut(), env.clone(), admin, prune).unwrap();
+        assert_eq!(res.attributes[3], attr("done", "true"));
+        assert!(!MARKETS.has(deps.as_ref().storage, "market".to_string()));
+        assert!(STAKES
+            .prefix("market".to_string())
+            .keys(deps.as_ref().storage, None, None, Order::Ascending)
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn test_execute_market_void() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+        create_market_poll(deps.as_mut(), env.clone());
+        stake(deps.as_mut(), env.clone(), ADDR2, "Juno", 100);
+
+        // chưa có kết quả thì chưa claim được
+        let claim = ExecuteMsg::ClaimPayout {
+            poll_id: "market".to_string(),
+        };
+        let info = message_info(&Addr::unchecked(ADDR2), &[]);
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), claim.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::PollNotEnded {}));
+
+        let msg = ExecuteMsg::VoidPoll {
+            poll_id: "market".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), admin, msg).unwrap();
+
+        // market bị huỷ -> hoàn stake
+        let res = execute(deps.as_mut(), env, info, claim).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: ADDR2.to_string(),
+                amount: vec![coin(100, "ucosm")],
+            })
+        );
+    }
+
+    #[test]
+    fn test_execute_pause() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+        let info = message_info(&Addr::unchecked(ADDR2), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+
+        // chỉ dừng voting, tự hết pause sau 10 block
+        let pause = ExecuteMsg::Pause {
+            scopes: Some(vec![PauseScope::Voting]),
+            until_height: Some(env.block.height + 10),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), pause.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let _res = execute(deps.as_mut(), env.clone(), admin.clone(), pause).unwrap();
+
+        // tạo poll vẫn được
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let vote = ExecuteMsg::Vote {
+            poll_id: "some_id".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), vote.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Paused {}));
+
+        // query vẫn chạy khi pause
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id".to_string(),
+        };
+        let _bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+
+        // tới until_height thì tự hết pause
+        env.block.height += 10;
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), vote.clone()).unwrap();
+
+        // pause tất cả rồi unpause
+        let pause = ExecuteMsg::Pause {
+            scopes: None,
+            until_height: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), admin.clone(), pause).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), vote.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Paused {}));
+        let _res = execute(deps.as_mut(), env.clone(), admin, ExecuteMsg::Unpause {}).unwrap();
+        let voter = message_info(&Addr::unchecked(ADDR3), &[]);
+        let _res = execute(deps.as_mut(), env, voter, vote).unwrap();
+    }
+
+    #[test]
+    fn test_execute_roles() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+        let owner = message_info(&Addr::unchecked(ADDR2), &[]);
+        let info = message_info(&Addr::unchecked(ADDR3), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+
+        // chỉ Owner được cấp role
+        let grant = ExecuteMsg::GrantRole {
+            address: ADDR2.to_string(),
+            role: Role::Owner,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), grant.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let _res = execute(deps.as_mut(), env.clone(), admin.clone(), grant).unwrap();
+
+        // Owner mới bật restricted creation
+        let msg = ExecuteMsg::UpdateConfig {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+            market_fee: None,
+            restricted_creation: Some(true),
+            report_threshold: None,
+            categories: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), owner.clone(), msg).unwrap();
+
+        let create = ExecuteMsg::CreatePoll {
+            poll_id: "some_id".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: Some(Eligibility::Electorate),
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), create.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        for role in [Role::PollCreator, Role::ElectorateManager] {
+            let msg = ExecuteMsg::GrantRole {
+                address: ADDR3.to_string(),
+                role,
+            };
+            let _res = execute(deps.as_mut(), env.clone(), owner.clone(), msg).unwrap();
+        }
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), create).unwrap();
+
+        let msg = QueryMsg::Roles {
+            address: ADDR3.to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: RolesResponse = from_json(&bin).unwrap();
+        assert_eq!(res.roles, vec![Role::PollCreator, Role::ElectorateManager]);
+
+        let msg = QueryMsg::RoleMembers {
+            role: Role::PollCreator,
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: RoleMembersResponse = from_json(&bin).unwrap();
+        assert_eq!(res.members, vec![Addr::unchecked(ADDR3)]);
+
+        // poll chỉ cho electorate vote
+        let vote = ExecuteMsg::Vote {
+            poll_id: "some_id".to_string(),
+            vote: VoteOption::Label("Juno".to_string()),
+        };
+        let voter = message_info(&Addr::unchecked(ADDR4), &[]);
+        let err = execute(deps.as_mut(), env.clone(), voter.clone(), vote.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::NotEligible {}));
+
+        let msg = ExecuteMsg::UpdateElectorate {
+            add: vec![ADDR4.to_string()],
+            remove: vec![],
+        };
+        let err = execute(deps.as_mut(), env.clone(), voter.clone(), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let _res = execute(deps.as_mut(), env.clone(), voter, vote).unwrap();
+
+        let msg = QueryMsg::Electorate {
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: ElectorateResponse = from_json(&bin).unwrap();
+        assert_eq!(res.voters, vec![Addr::unchecked(ADDR4)]);
+
+        // moderator ẩn và huỷ poll, PollCreator thì không
+        let hide = ExecuteMsg::HidePoll {
+            poll_id: "some_id".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), hide.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let msg = ExecuteMsg::GrantRole {
+            address: ADDR3.to_string(),
+            role: Role::Moderator,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), owner.clone(), msg).unwrap();
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), hide).unwrap();
+        let cancel = ExecuteMsg::CancelPoll {
+            poll_id: "some_id".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), cancel).unwrap();
+
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResponse = from_json(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert!(poll.hidden);
+        assert_eq!(poll.status, PollStatus::Cancelled);
+
+        // thu hồi role
+        let msg = ExecuteMsg::RevokeRole {
+            address: ADDR3.to_string(),
+            role: Role::PollCreator,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), owner, msg).unwrap();
+        let msg = QueryMsg::Roles {
+            address: ADDR3.to_string(),
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: RolesResponse = from_json(&bin).unwrap();
+        assert_eq!(res.roles, vec![Role::ElectorateManager, Role::Moderator]);
+    }
+
+    #[test]
+    fn test_execute_report_poll() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+        let info = message_info(&Addr::unchecked(ADDR2), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+
+        // 2 report thì tự ẩn
+        let msg = ExecuteMsg::UpdateConfig {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+            market_fee: None,
+            restricted_creation: None,
+            report_threshold: Some(2),
+            categories: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let report = ExecuteMsg::ReportPoll {
+            poll_id: "some_id".to_string(),
+            reason: "spam".to_string(),
+        };
+        let reporter = message_info(&Addr::unchecked(ADDR3), &[]);
+        let _res = execute(deps.as_mut(), env.clone(), reporter.clone(), report.clone()).unwrap();
+        // mỗi address chỉ report một lần
+        let err = execute(deps.as_mut(), env.clone(), reporter, report.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::AlreadyReported {}));
+
+        let msg = ExecuteMsg::ReportPoll {
+            poll_id: "some_id".to_string(),
+            reason: "x".repeat(257),
+        };
+        let reporter = message_info(&Addr::unchecked(ADDR4), &[]);
+        let err = execute(deps.as_mut(), env.clone(), reporter.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::ReasonTooLong {}));
+        let _res = execute(deps.as_mut(), env.clone(), reporter, report.clone()).unwrap();
+
+        let msg = QueryMsg::AllPoll {
+            include_hidden: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: AllPollsResponse = from_json(&bin).unwrap();
+        assert!(res.polls.is_empty());
+
+        let msg = QueryMsg::AllPoll {
+            include_hidden: Some(true),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: AllPollsResponse = from_json(&bin).unwrap();
+        assert_eq!(res.polls.len(), 1);
+        assert_eq!(res.polls[0].reports, 2);
+        assert!(res.polls[0].hidden);
+
+        let msg = QueryMsg::Reports {
+            poll_id: "some_id".to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: ReportsResponse = from_json(&bin).unwrap();
+        assert_eq!(
+            res.reports,
+            vec![
+                (Addr::unchecked(ADDR3), "spam".to_string()),
+                (Addr::unchecked(ADDR4), "spam".to_string())
+            ]
+        );
+
+        // moderator xem lại rồi bỏ ẩn
+        let unhide = ExecuteMsg::UnhidePoll {
+            poll_id: "some_id".to_string(),
+        };
+        let reporter = message_info(&Addr::unchecked(ADDR3), &[]);
+        let err = execute(deps.as_mut(), env.clone(), reporter, unhide.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let _res = execute(deps.as_mut(), env.clone(), admin, unhide).unwrap();
+
+        let msg = QueryMsg::AllPoll {
+            include_hidden: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg.clone()).unwrap();
+        let res: AllPollsResponse = from_json(&bin).unwrap();
+        assert_eq!(res.polls.len(), 1);
+        assert_eq!(res.polls[0].reports, 0);
+
+        // report mới sau khi bỏ ẩn chưa đủ ngưỡng nên poll vẫn hiện
+        let reporter = message_info(&Addr::unchecked("reporter"), &[]);
+        let res = execute(deps.as_mut(), env.clone(), reporter, report).unwrap();
+        assert_eq!(res.attributes[2], attr("reports", "1"));
+        assert_eq!(res.attributes[3], attr("hidden", "false"));
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: AllPollsResponse = from_json(&bin).unwrap();
+        assert_eq!(res.polls.len(), 1);
+    }
+
+    #[test]
+    fn test_execute_create_poll_metadata() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let metadata = PollMetadata {
+            description: Some("Pick the chain you use the most".to_string()),
+            external_url: Some("https://forum.cosmos.network/t/1".to_string()),
+            image_uri: Some("ipfs://bafy/poll.png".to_string()),
+            category: Some("ecosystem".to_string()),
+            tags: vec!["cosmos".to_string(), "juno".to_string()],
+        };
+        let option_metadata = vec![
+            OptionMetadata {
+                description: Some("The Hub".to_string()),
+                uri: Some("https://cosmos.network".to_string()),
+            },
+            OptionMetadata::default(),
+        ];
+        let create =
+            |metadata: PollMetadata, option_metadata: Vec<OptionMetadata>| ExecuteMsg::CreatePoll {
+                poll_id: "some_id".to_string(),
+                question: "What's your favourite Cosmos coin?".to_string(),
+                options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+                voting_mode: None,
+                reward_split: None,
+                market: None,
+                eligibility: None,
+                metadata: Some(metadata),
+                option_metadata: Some(option_metadata),
+                vote_change: None,
+                duration: None,
+            };
+
+        // vượt giới hạn độ dài
+        let mut long = metadata.clone();
+        long.external_url = Some("x".repeat(257));
+        let msg = create(long, option_metadata.clone());
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::MetadataTooLong { field } if field == "external_url"));
+
+        let mut long = metadata.clone();
+        long.tags = vec!["tag".to_string(); 11];
+        let msg = create(long, option_metadata.clone());
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidMetadata {}));
+
+        // số metadata phải khớp số option
+        let msg = create(metadata.clone(), vec![OptionMetadata::default()]);
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidMetadata {}));
+
+        let msg = create(metadata.clone(), option_metadata.clone());
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id".to_string(),
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: PollResponse = from_json(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.metadata, metadata);
+        assert_eq!(res.options[0].0.description, option_metadata[0].description);
+        assert_eq!(res.options[0].0.uri, option_metadata[0].uri);
+        assert_eq!(res.options[1].0.uri, None);
+    }
+
+    #[test]
+    fn test_execute_update_poll() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id".to_string(),
+            question: "What's your favourite Cosmos con?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let update = ExecuteMsg::UpdatePoll {
+            poll_id: "some_id".to_string(),
+            question: Some("What's your favourite Cosmos coin?".to_string()),
+            options: Some(vec![
+                "Cosmos Hub".to_string(),
+                "Juno".to_string(),
+                "Osmosis".to_string(),
+            ]),
+            metadata: None,
+            option_metadata: None,
+        };
+        // chỉ creator được sửa
+        let other = message_info(&Addr::unchecked(ADDR2), &[]);
+        let err = execute(deps.as_mut(), env.clone(), other.clone(), update.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        env.block.height += 5;
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), update.clone()).unwrap();
+
+        let msg = QueryMsg::PollHistory {
+            poll_id: "some_id".to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollHistoryResponse = from_json(&bin).unwrap();
+        assert_eq!(res.revisions.len(), 2);
+        assert_eq!(
+            res.revisions[0].question,
+            "What's your favourite Cosmos con?"
+        );
+        assert_eq!(res.revisions[0].height, 12345);
+        assert_eq!(res.revisions[1].revision, 1);
+        assert_eq!(res.revisions[1].height, 12350);
+        assert_eq!(res.revisions[1].options.len(), 3);
+
+        // có phiếu rồi thì không sửa được
+        let msg = ExecuteMsg::Vote {
+            poll_id: "some_id".to_string(),
+            vote: VoteOption::Label("Osmosis".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), other, msg).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), info, update).unwrap_err();
+        assert!(matches!(err, ContractError::PollHasVotes {}));
+
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id".to_string(),
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: PollResponse = from_json(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.revision, 1);
+        assert_eq!(res.options[2].0.label, "Osmosis");
+        assert_eq!(res.options[2].1, 1);
+    }
+
+    #[test]
+    fn test_execute_delete_poll() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+        let other = message_info(&Addr::unchecked(ADDR2), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let create = ExecuteMsg::CreatePoll {
+            poll_id: "some_id".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), other.clone(), create.clone()).unwrap();
+        for voter in [ADDR2, ADDR3, ADDR4] {
+            let msg = ExecuteMsg::Vote {
+                poll_id: "some_id".to_string(),
+                vote: VoteOption::Label("Juno".to_string()),
+            };
+            let voter = message_info(&Addr::unchecked(voter), &[]);
+            let _res = execute(deps.as_mut(), env.clone(), voter, msg).unwrap();
+        }
+
+        let delete = ExecuteMsg::DeletePoll {
+            poll_id: "some_id".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), other.clone(), delete.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::PollNotEnded {}));
+        let prune = ExecuteMsg::PruneBallots {
+            poll_id: "some_id".to_string(),
+            limit: Some(2),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), prune.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::PollNotDeleted {}));
+
+        let msg = ExecuteMsg::FinalizePoll {
+            poll_id: "some_id".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), other.clone(), msg).unwrap();
+        let voter = message_info(&Addr::unchecked(ADDR3), &[]);
+        let err = execute(deps.as_mut(), env.clone(), voter, delete.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let _res = execute(deps.as_mut(), env.clone(), other.clone(), delete).unwrap();
+
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResponse = from_json(&bin).unwrap();
+        assert!(res.poll.is_none());
+
+        let msg = QueryMsg::PollResult {
+            poll_id: "some_id".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResultResponse = from_json(&bin).unwrap();
+        let result = res.result.unwrap();
+        assert_eq!(result.status, PollStatus::Finalized);
+        assert_eq!(result.options[1], ("Juno".to_string(), 3));
+
+        let msg = ExecuteMsg::PruneBallots {
+            poll_id: "some_id".to_string(),
+            limit: Some(0),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidLimit {}));
+
+        // 3 ballot + 1 revision + 1 checkpoint turnout + 2 changelog tally, xoá theo lô 2 record
+        for _ in 0..3 {
+            let res = execute(deps.as_mut(), env.clone(), info.clone(), prune.clone()).unwrap();
+            assert_eq!(res.attributes[2], attr("pruned", "2"));
+            assert_eq!(res.attributes[3], attr("done", "false"));
+        }
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), prune).unwrap();
+        assert_eq!(res.attributes[2], attr("pruned", "1"));
+        assert_eq!(res.attributes[3], attr("done", "true"));
+
+        let msg = QueryMsg::Vote {
+            poll_id: "some_id".to_string(),
+            address: ADDR4.to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: VoteResponse = from_json(&bin).unwrap();
+        assert!(res.vote.is_none());
+
+        // id của poll đã xoá không dùng lại được
+        let err = execute(deps.as_mut(), env, other, create).unwrap_err();
+        assert!(matches!(err, ContractError::PollAlreadyExists { .. }));
+    }
+
+    #[test]
+    fn test_query_polls_by_tag_and_search() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::UpdateConfig {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+            market_fee: None,
+            restricted_creation: None,
+            report_threshold: None,
+            categories: Some(vec!["ecosystem".to_string(), "governance".to_string()]),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let create =
+            |poll_id: &str, question: &str, category: &str, tags: &[&str]| ExecuteMsg::CreatePoll {
+                poll_id: poll_id.to_string(),
+                question: question.to_string(),
+                options: vec!["Yes".to_string(), "No".to_string()],
+                voting_mode: None,
+                reward_split: None,
+                market: None,
+                eligibility: None,
+                metadata: Some(PollMetadata {
+                    category: Some(category.to_string()),
+                    tags: tags.iter().map(|t| t.to_string()).collect(),
+                    ..PollMetadata::default()
+                }),
+                option_metadata: None,
+                vote_change: None,
+                duration: None,
+            };
+
+        // category phải nằm trong danh sách admin cấu hình
+        let msg = create("poll_0", "Is this spam?", "memes", &[]);
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidCategory {}));
+
+        let polls = [
+            (
+                "poll_1",
+                "Should Juno upgrade?",
+                "governance",
+                vec!["Juno", "upgrade"],
+            ),
+            (
+                "poll_2",
+                "Should  JUNO burn fees?",
+                "governance",
+                vec!["juno"],
+            ),
+            ("poll_3", "Best Cosmos wallet?", "ecosystem", vec!["wallet"]),
+        ];
+        for (poll_id, question, category, tags) in polls {
+            let msg = create(poll_id, question, category, &tags);
+            let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        }
+
+        let msg = QueryMsg::PollsByTag {
+            tag: "JUNO".to_string(),
+            start_after: None,
+            limit: None,
+            include_hidden: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollsResponse = from_json(&bin).unwrap();
+        let ids: Vec<_> = res.polls.iter().map(|p| p.0.as_str()).collect();
+        assert_eq!(ids, vec!["poll_1", "poll_2"]);
+
+        let msg = QueryMsg::PollsByTag {
+            tag: "juno".to_string(),
+            start_after: Some("poll_1".to_string()),
+            limit: None,
+            include_hidden: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollsResponse = from_json(&bin).unwrap();
+        assert_eq!(res.polls.len(), 1);
+        assert_eq!(res.polls[0].0, "poll_2");
+
+        // sửa tag thì index cũng đổi theo
+        let msg = ExecuteMsg::UpdatePoll {
+            poll_id: "poll_2".to_string(),
+            question: None,
+            options: None,
+            metadata: Some(PollMetadata {
+                category: Some("governance".to_string()),
+                tags: vec!["fees".to_string()],
+                ..PollMetadata::default()
+            }),
+            option_metadata: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let msg = QueryMsg::PollsByTag {
+            tag: "juno".to_string(),
+            start_after: None,
+            limit: None,
+            include_hidden: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollsResponse = from_json(&bin).unwrap();
+        assert_eq!(res.polls.len(), 1);
+
+        // tìm theo prefix không phân biệt hoa thường và khoảng trắng
+        let msg = QueryMsg::SearchPolls {
+            prefix: "should juno".to_string(),
+            start_after: None,
+            limit: Some(1),
+            include_hidden: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollsResponse = from_json(&bin).unwrap();
+        assert_eq!(res.polls.len(), 1);
+        assert_eq!(res.polls[0].0, "poll_2");
+
+        let msg = QueryMsg::SearchPolls {
+            prefix: "should juno".to_string(),
+            start_after: Some("poll_2".to_string()),
+            limit: None,
+            include_hidden: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollsResponse = from_json(&bin).unwrap();
+        let ids: Vec<_> = res.polls.iter().map(|p| p.0.as_str()).collect();
+        assert_eq!(ids, vec!["poll_1"]);
+
+        let msg = QueryMsg::SearchPolls {
+            prefix: "best".to_string(),
+            start_after: None,
+            limit: None,
+            include_hidden: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollsResponse = from_json(&bin).unwrap();
+        assert_eq!(res.polls.len(), 1);
+        assert_eq!(res.polls[0].0, "poll_3");
+
+        // poll bị ẩn được lọc trước khi cắt trang, trang vẫn đủ limit
+        let msg = ExecuteMsg::HidePoll {
+            poll_id: "poll_2".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let msg = QueryMsg::SearchPolls {
+            prefix: "should juno".to_string(),
+            start_after: None,
+            limit: Some(1),
+            include_hidden: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollsResponse = from_json(&bin).unwrap();
+        let ids: Vec<_> = res.polls.iter().map(|p| p.0.as_str()).collect();
+        assert_eq!(ids, vec!["poll_1"]);
+
+        // poll làm cursor bị xoá giữa hai trang vẫn dùng được làm mốc
+        let msg = ExecuteMsg::FinalizePoll {
+            poll_id: "poll_2".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::DeletePoll {
+            poll_id: "poll_2".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let msg = QueryMsg::SearchPolls {
+            prefix: "should juno".to_string(),
+            start_after: Some("poll_2".to_string()),
+            limit: None,
+            include_hidden: Some(true),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollsResponse = from_json(&bin).unwrap();
+        let ids: Vec<_> = res.polls.iter().map(|p| p.0.as_str()).collect();
+        assert_eq!(ids, vec!["poll_1"]);
+        let msg = QueryMsg::PollsByTag {
+            tag: "juno".to_string(),
+            start_after: Some("poll_0".to_string()),
+            limit: Some(1),
+            include_hidden: None,
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: PollsResponse = from_json(&bin).unwrap();
+        let ids: Vec<_> = res.polls.iter().map(|p| p.0.as_str()).collect();
+        assert_eq!(ids, vec!["poll_1"]);
+    }
+
+    #[test]
+    fn test_execute_create_poll_from_template() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+        let info = message_info(&Addr::unchecked(ADDR2), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+
+        let save = ExecuteMsg::SaveTemplate {
+            template_id: "weekly".to_string(),
+            template: Template {
+                question: "How was sprint {week}?".to_string(),
+                options: vec![
+                    "Good".to_string(),
+                    "Bad".to_string(),
+                    "Skip {week}".to_string(),
+                ],
+                voting_mode: TemplateVotingMode::Public,
+                duration: Some(100),
+                eligibility: Eligibility::Anyone,
+            },
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), save.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let _res = execute(deps.as_mut(), env.clone(), admin.clone(), save).unwrap();
+
+        // commit-reveal không dùng duration
+        let msg = ExecuteMsg::SaveTemplate {
+            template_id: "secret".to_string(),
+            template: Template {
+                question: "Secret poll {week}".to_string(),
+                options: vec!["Yes".to_string(), "No".to_string()],
+                voting_mode: TemplateVotingMode::CommitReveal {
+                    commit_blocks: 10,
+                    reveal_blocks: 10,
+                },
+                duration: Some(100),
+                eligibility: Eligibility::Anyone,
+            },
+        };
+        let err = execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidPhases {}));
+
+        let params = BTreeMap::from([("week".to_string(), "42".to_string())]);
+        let msg = ExecuteMsg::CreatePollFromTemplate {
+            template_id: "monthly".to_string(),
+            poll_id: "sprint_42".to_string(),
+            params: params.clone(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::TemplateNotFound { .. }));
+
+        let msg = ExecuteMsg::CreatePollFromTemplate {
+            template_id: "weekly".to_string(),
+            poll_id: "sprint_42".to_string(),
+            params,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = QueryMsg::Poll {
+            poll_id: "sprint_42".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResponse = from_json(&bin).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.creator, Addr::unchecked(ADDR2));
+        assert_eq!(poll.question, "How was sprint 42?");
+        assert_eq!(res.options[2].0.label, "Skip 42");
+        assert_eq!(poll.end_height, Some(12445));
+
+        let vote = ExecuteMsg::Vote {
+            poll_id: "sprint_42".to_string(),
+            vote: VoteOption::Label("Good".to_string()),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), vote.clone()).unwrap();
+
+        // hết duration thì không vote được nữa
+        env.block.height += 101;
+        let err = execute(deps.as_mut(), env.clone(), admin, vote).unwrap_err();
+        assert!(matches!(err, ContractError::PollNotOpen {}));
+
+        let msg = QueryMsg::Templates {
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: TemplatesResponse = from_json(&bin).unwrap();
+        assert_eq!(res.templates.len(), 1);
+        assert_eq!(res.templates[0].0, "weekly");
+    }
+
+    #[test]
+    fn test_execute_advance_series() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+        let cranker = message_info(&Addr::unchecked(ADDR3), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: Some(coin(100, "ujuno")),
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::SaveTemplate {
+            template_id: "weekly".to_string(),
+            template: Template {
+                question: "{team} weekly #{instance}".to_string(),
+                options: vec!["Good".to_string(), "Bad".to_string()],
+                voting_mode: TemplateVotingMode::Public,
+                duration: None,
+                eligibility: Eligibility::Anyone,
+            },
+        };
+        let _res = execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+
+        // poll của series không cần tiền cọc
+        let msg = ExecuteMsg::CreateSeries {
+            series_id: "infra".to_string(),
+            template_id: "weekly".to_string(),
+            params: BTreeMap::from([("team".to_string(), "Infra".to_string())]),
+            period: 100,
+            bounty: Some(coin(10, "ujuno")),
+        };
+        let funded = message_info(&Addr::unchecked(ADDR1), &[coin(15, "ujuno")]);
+        let _res = execute(deps.as_mut(), env.clone(), funded, msg).unwrap();
+
+        let msg = QueryMsg::Poll {
+            poll_id: "infra#1".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResponse = from_json(&bin).unwrap();
+        assert_eq!(res.poll.unwrap().question, "Infra weekly #1");
+
+        // user không chiếm trước được poll_id của instance tiếp theo
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "infra#2".to_string(),
+            question: "Squatting?".to_string(),
+            options: vec!["Yes".to_string(), "No".to_string()],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let info = message_info(&Addr::unchecked(ADDR2), &[coin(100, "ujuno")]);
+        let err = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidPollId {}));
+
+        let advance = ExecuteMsg::AdvanceSeries {
+            series_id: "infra".to_string(),
+        };
+        let err =
+            execute(deps.as_mut(), env.clone(), cranker.clone(), advance.clone()).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::SeriesNotDue { next_height: 12445 }
+        ));
+
+        env.block.height += 100;
+        let res = execute(deps.as_mut(), env.clone(), cranker.clone(), advance.clone()).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: ADDR3.to_string(),
+                amount: vec![coin(10, "ujuno")],
+            })
+        );
+
+        let msg = QueryMsg::Poll {
+            poll_id: "infra#1".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResponse = from_json(&bin).unwrap();
+        assert_eq!(res.poll.unwrap().status, PollStatus::Finalized);
+
+        // quỹ chỉ còn 5
+        env.block.height += 100;
+        let res = execute(deps.as_mut(), env.clone(), cranker.clone(), advance.clone()).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: ADDR3.to_string(),
+                amount: vec![coin(5, "ujuno")],
+            })
+        );
+        env.block.height += 100;
+        let res = execute(deps.as_mut(), env.clone(), cranker, advance).unwrap();
+        assert!(res.messages.is_empty());
+
+        let msg = QueryMsg::SeriesHistory {
+            series_id: "infra".to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: SeriesHistoryResponse = from_json(&bin).unwrap();
+        assert_eq!(res.instances.len(), 4);
+        assert_eq!(res.instances[0].poll_id, "infra#1");
+        assert_eq!(res.instances[0].closed_height, Some(12445));
+        assert_eq!(res.instances[3].opened_height, 12645);
+        assert_eq!(res.instances[3].closed_height, None);
+
+        // ai cũng nạp thêm quỹ được, nhưng phải đúng denom của bounty
+        let msg = ExecuteMsg::FundSeries {
+            series_id: "infra".to_string(),
+        };
+        let info = message_info(&Addr::unchecked(ADDR3), &[coin(20, "uatom")]);
+        let err = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidRewardPool {}));
+        let info = message_info(&Addr::unchecked(ADDR3), &[coin(20, "ujuno")]);
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // owner khác dừng series: quỹ còn lại về funder, poll đang mở được đóng
+        let msg = ExecuteMsg::GrantRole {
+            address: ADDR2.to_string(),
+            role: Role::Owner,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), admin, msg).unwrap();
+        let msg = ExecuteMsg::StopSeries {
+            series_id: "infra".to_string(),
+        };
+        let owner = message_info(&Addr::unchecked(ADDR2), &[]);
+        let res = execute(deps.as_mut(), env.clone(), owner, msg).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: ADDR1.to_string(),
+                amount: vec![coin(20, "ujuno")],
+            })
+        );
+
+        let msg = QueryMsg::Poll {
+            poll_id: "infra#4".to_string(),
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: PollResponse = from_json(&bin).unwrap();
+        assert_eq!(res.poll.unwrap().status, PollStatus::Finalized);
+        let msg = QueryMsg::SeriesHistory {
+            series_id: "infra".to_string(),
+            start_after: None,
+            limit: None,
+        };
+        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let res: SeriesHistoryResponse = from_json(&bin).unwrap();
+        assert_eq!(res.instances[3].closed_height, Some(12645));
+
+        let msg = QueryMsg::Series {
+            series_id: "infra".to_string(),
+        };
+        let bin = query(deps.as_ref(), env, msg).unwrap();
+        let res: SeriesResponse = from_json(&bin).unwrap();
+        assert!(res.series.is_none());
+    }
+
+    // Storage đếm số lần / số byte đọc ghi để ước lượng gas theo KVGasConfig của Cosmos SDK
+    #[derive(Default)]
+    struct GasStorage {
+        inner: MockStorage,
+        reads: Cell<u64>,
+        read_bytes: Cell<u64>,
+        writes: u64,
+        write_bytes: u64,
+    }
+
+    impl GasStorage {
+        fn reset(&mut self) {
+            self.reads.set(0);
+            self.read_bytes.set(0);
+            self.writes = 0;
+            self.write_bytes = 0;
+        }
+
+        // ReadCostFlat 1000, ReadCostPerByte 3, WriteCostFlat 2000, WriteCostPerByte 30
+        fn gas(&self) -> u64 {
+            self.reads.get() * 1000
+                + self.read_bytes.get() * 3
+                + self.writes * 2000
+                + self.write_bytes * 30
+        }
+    }
+
+    impl Storage for GasStorage {
+        fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+            let value = self.inner.get(key);
+            self.reads.set(self.reads.get() + 1);
+            let len = value.as_ref().map_or(0, |v| v.len());
+            self.read_bytes
+                .set(self.read_bytes.get() + (key.len() + len) as u64);
+            value
+        }
+
+        fn range<'a>(
+            &'a self,
+            start: Option<&[u8]>,
+            end: Option<&[u8]>,
+            order: Order,
+        ) -> Box<dyn Iterator<Item = Record> + 'a> {
+            Box::new(self.inner.range(start, end, order).inspect(|(k, v)| {
+                self.reads.set(self.reads.get() + 1);
+                self.read_bytes
+                    .set(self.read_bytes.get() + (k.len() + v.len()) as u64);
+            }))
+        }
+
+        fn set(&mut self, key: &[u8], value: &[u8]) {
+            self.writes += 1;
+            self.write_bytes += (key.len() + value.len()) as u64;
+            self.inner.set(key, value);
+        }
+
+        fn remove(&mut self, key: &[u8]) {
+            self.writes += 1;
+            self.inner.remove(key);
+        }
+    }
+
+    // (gas, byte ghi) của lần vote đầu và lần đổi vote trên poll có câu hỏi dài `question_len`
+    fn bench_vote(question_len: usize) -> [(u64, u64); 2] {
+        let mut deps = OwnedDeps {
+            storage: GasStorage::default(),
+            api: MockApi::default(),
+            querier: MockQuerier::default(),
+            custom_query_type: PhantomData::<Empty>,
+        };
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "some_id".to_string(),
+            question: "q".repeat(question_len),
+            options: (0..10).map(|i| format!("{:0>50}", i)).collect(),
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let mut result = [(0, 0); 2];
+        for (i, option) in [9, 0].iter().enumerate() {
+            deps.storage.reset();
+            let msg = ExecuteMsg::Vote {
+                poll_id: "some_id".to_string(),
+                vote: VoteOption::Index(*option),
+            };
+            let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+            result[i] = (deps.storage.gas(), deps.storage.write_bytes);
+        }
+        result
+    }
+
+    // như bench_vote nhưng theo cách vote của bản 0.1.x: đọc cả poll, sửa số phiếu rồi ghi lại cả poll
+    fn bench_legacy_vote(question_len: usize) -> [(u64, u64); 2] {
+        let mut storage = GasStorage::default();
+        let voter = Addr::unchecked(ADDR1);
+        let options: Vec<String> = (0..10).map(|i| format!("{:0>50}", i)).collect();
+        let poll = LegacyPoll {
+            creator: voter.clone(),
+            question: "q".repeat(question_len),
+            options: options.iter().map(|option| (option.clone(), 0)).collect(),
+        };
+        LEGACY_POLLS
+            .save(&mut storage, "some_id".to_string(), &poll)
+            .unwrap();
+
+        let mut result = [(0, 0); 2];
+        for (i, option) in [9, 0].iter().enumerate() {
+            storage.reset();
+            let vote = options[*option].clone();
+            let mut poll = LEGACY_POLLS.load(&storage, "some_id".to_string()).unwrap();
+            LEGACY_BALLOTS
+                .update(
+                    &mut storage,
+                    (voter.clone(), "some_id".to_string()),
+                    |ballot| -> StdResult<_> {
+                        if let Some(ballot) = ballot {
+                            let old = poll
+                                .options
+                                .iter()
+                                .position(|option| option.0 == ballot.option)
+                                .unwrap();
+                            poll.options[old].1 -= 1;
+                        }
+                        Ok(LegacyBallot {
+                            option: vote.clone(),
+                        })
+                    },
+                )
+                .unwrap();
+            let position = poll
+                .options
+                .iter()
+                .position(|option| option.0 == vote)
+                .unwrap();
+            poll.options[position].1 += 1;
+            LEGACY_POLLS
+                .save(&mut storage, "some_id".to_string(), &poll)
+                .unwrap();
+            result[i] = (storage.gas(), storage.write_bytes);
+        }
+        result
+    }
+
+    #[test]
+    fn test_vote_gas() {
+        let short = bench_vote(16);
+        let long = bench_vote(2048);
+
+        // số byte ghi không phụ thuộc độ dài câu hỏi
+        assert_eq!(short[0].1, long[0].1);
+        assert_eq!(short[1].1, long[1].1);
+        // câu hỏi chỉ còn bị đọc đúng một lần (ReadCostPerByte = 3)
+        assert_eq!(long[0].0 - short[0].0, 3 * (2048 - 16));
+        assert_eq!(long[1].0 - short[1].0, 3 * (2048 - 16));
+        // lần vote đầu còn cập nhật bộ đếm STATS và VOTERS, đổi phiếu thì không
+        assert!(long[1].0 < long[0].0);
+
+        // cách cũ ghi lại cả câu hỏi mỗi lần vote (WriteCostPerByte = 30)
+        let legacy_short = bench_legacy_vote(16);
+        let legacy_long = bench_legacy_vote(2048);
+        assert_eq!(legacy_long[0].1 - legacy_short[0].1, 2048 - 16);
+        assert_eq!(legacy_long[1].1 - legacy_short[1].1, 2048 - 16);
+        // với câu hỏi dài, vote lần đầu và đổi phiếu đều tốn chưa tới một nửa gas so với cách cũ
+        assert!(long[0].0 * 2 < legacy_long[0].0);
+        assert!(long[1].0 * 2 < legacy_long[1].0);
+    }
+
+    #[test]
+    fn test_execute_vote_by_index() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-        // Create a poll
         let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id_1".to_string(),
+            poll_id: "some_id".to_string(),
             question: "What's your favourite Cosmos coin?".to_string(),
-            options: vec![
-                "Cosmos Hub".to_string(),
-                "Juno".to_string(),
-                "Osmosis".to_string(),
-            ],
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: None,
+            duration: None,
         };
         let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-        // Create a vote
         let msg = ExecuteMsg::Vote {
-            poll_id: "some_id_1".to_string(),
-            vote: "Juno".to_string(),
+            poll_id: "some_id".to_string(),
+            vote: VoteOption::Index(2),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::InvalidOptionIndex { index: 2, count: 2 }
+        ));
+
+        let msg = ExecuteMsg::Vote {
+            poll_id: "some_id".to_string(),
+            vote: VoteOption::Index(1),
         };
         let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-        // Query for a vote that exists
+        // label và index cùng trỏ tới một ballot
+        let msg = ExecuteMsg::Vote {
+            poll_id: "some_id".to_string(),
+            vote: VoteOption::Label("Cosmos Hub".to_string()),
+        };
+        let voter = message_info(&Addr::unchecked(ADDR2), &[]);
+        let _res = execute(deps.as_mut(), env.clone(), voter, msg).unwrap();
+
         let msg = QueryMsg::Vote {
-            poll_id: "some_id_1".to_string(),
+            poll_id: "some_id".to_string(),
             address: ADDR1.to_string(),
         };
-        let bin = query(deps.as_ref(), env.clone(), msg).unwrap();
-        let res: VoteResponse = from_binary(&bin).unwrap();
-        // Expect the vote to exist
-        assert!(res.vote.is_some());
+        let res: VoteResponse = from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.vote.unwrap().option, 1);
+
+        let msg = QueryMsg::Poll {
+            poll_id: "some_id".to_string(),
+        };
+        let res: PollResponse = from_json(query(deps.as_ref(), env, msg).unwrap()).unwrap();
+        assert_eq!(res.options[0].1, 1);
+        assert_eq!(res.options[1].1, 1);
+    }
+
+    fn create_policy_poll(deps: DepsMut, env: Env, poll_id: &str, policy: VoteChangePolicy) {
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: poll_id.to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: None,
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: Some(policy),
+            duration: None,
+        };
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+        execute(deps, env, info, msg).unwrap();
+    }
+
+    fn vote_index(poll_id: &str, index: u32) -> ExecuteMsg {
+        ExecuteMsg::Vote {
+            poll_id: poll_id.to_string(),
+            vote: VoteOption::Index(index),
+        }
+    }
+
+    #[test]
+    fn test_execute_vote_change_policy() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+        let voter = message_info(&Addr::unchecked(ADDR2), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+        create_policy_poll(
+            deps.as_mut(),
+            env.clone(),
+            "immutable",
+            VoteChangePolicy::Immutable,
+        );
+        create_policy_poll(
+            deps.as_mut(),
+            env.clone(),
+            "until",
+            VoteChangePolicy::ChangeableUntil(Deadline::Height(env.block.height + 5)),
+        );
+        create_policy_poll(
+            deps.as_mut(),
+            env.clone(),
+            "retractable",
+            VoteChangePolicy::Retractable,
+        );
+        create_policy_poll(
+            deps.as_mut(),
+            env.clone(),
+            "changeable",
+            VoteChangePolicy::Changeable,
+        );
+
+        // Immutable: chỉ vote một lần
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            voter.clone(),
+            vote_index("immutable", 0),
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            voter.clone(),
+            vote_index("immutable", 1),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::VoteChangeNotAllowed {}));
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            voter.clone(),
+            vote_index("immutable", 0),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::AlreadyVoted {}));
+
+        // ChangeableUntil: đổi được trước deadline, sau đó bị khoá
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            voter.clone(),
+            vote_index("until", 0),
+        )
+        .unwrap();
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            voter.clone(),
+            vote_index("until", 1),
+        )
+        .unwrap();
+        let mut late = env.clone();
+        late.block.height += 5;
+        let err = execute(
+            deps.as_mut(),
+            late.clone(),
+            voter.clone(),
+            vote_index("until", 0),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::VoteChangeNotAllowed {}));
+        // voter mới vẫn vote được sau deadline
+        let other = message_info(&Addr::unchecked(ADDR3), &[]);
+        let _res = execute(deps.as_mut(), late, other, vote_index("until", 0)).unwrap();
+
+        // chỉ poll Retractable mới rút phiếu được
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            voter.clone(),
+            vote_index("changeable", 0),
+        )
+        .unwrap();
+        let msg = ExecuteMsg::RetractVote {
+            poll_id: "changeable".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), voter.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::VoteChangeNotAllowed {}));
+
+        let retract = ExecuteMsg::RetractVote {
+            poll_id: "retractable".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), voter.clone(), retract.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::NoBallot {}));
+
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            voter.clone(),
+            vote_index("retractable", 1),
+        )
+        .unwrap();
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            voter.clone(),
+            vote_index("retractable", 0),
+        )
+        .unwrap();
+        let res = execute(deps.as_mut(), env.clone(), voter.clone(), retract.clone()).unwrap();
+        assert_eq!(res.attributes[0].value, "retract_vote");
 
-        // Query for a vote that does not exists
         let msg = QueryMsg::Vote {
-            poll_id: "some_id_2".to_string(),
+            poll_id: "retractable".to_string(),
             address: ADDR2.to_string(),
         };
-        let bin = query(deps.as_ref(), env, msg).unwrap();
-        let res: VoteResponse = from_binary(&bin).unwrap();
-        // Expect the vote to not exist
-        assert!(res.vote.is_none());
+        let res: VoteResponse = from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.vote, None);
+        let msg = QueryMsg::Poll {
+            poll_id: "retractable".to_string(),
+        };
+        let res: PollResponse = from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.options[0].1, 0);
+        assert_eq!(res.options[1].1, 0);
+
+        // rút rồi thì vote lại được
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            voter.clone(),
+            vote_index("retractable", 1),
+        )
+        .unwrap();
+
+        // poll đã finalize thì không rút được nữa
+        env.block.height += 1;
+        let msg = ExecuteMsg::FinalizePoll {
+            poll_id: "retractable".to_string(),
+        };
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+        let _res = execute(deps.as_mut(), env.clone(), admin, msg).unwrap();
+        let err = execute(deps.as_mut(), env.clone(), voter.clone(), retract).unwrap_err();
+        assert!(matches!(err, ContractError::PollNotOpen {}));
+
+        // poll commit-reveal: rút được trong reveal phase, hết reveal phase thì không
+        let msg = ExecuteMsg::CreatePoll {
+            poll_id: "secret".to_string(),
+            question: "What's your favourite Cosmos coin?".to_string(),
+            options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            voting_mode: Some(VotingMode::CommitReveal {
+                commit_end_height: env.block.height + 10,
+                reveal_end_height: env.block.height + 20,
+            }),
+            reward_split: None,
+            market: None,
+            eligibility: None,
+            metadata: None,
+            option_metadata: None,
+            vote_change: Some(VoteChangePolicy::Retractable),
+            duration: None,
+        };
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+        let _res = execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+        for sender in [&voter, &admin] {
+            let msg = ExecuteMsg::CommitVote {
+                poll_id: "secret".to_string(),
+                commitment: compute_commitment(1, "salt", &sender.sender),
+            };
+            let _res = execute(deps.as_mut(), env.clone(), sender.clone(), msg).unwrap();
+        }
+        env.block.height += 11;
+        for sender in [&voter, &admin] {
+            let msg = ExecuteMsg::RevealVote {
+                poll_id: "secret".to_string(),
+                vote: VoteOption::Index(1),
+                salt: "salt".to_string(),
+            };
+            let _res = execute(deps.as_mut(), env.clone(), sender.clone(), msg).unwrap();
+        }
+        let retract = ExecuteMsg::RetractVote {
+            poll_id: "secret".to_string(),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), voter, retract.clone()).unwrap();
+        env.block.height += 10;
+        let err = execute(deps.as_mut(), env, admin, retract).unwrap_err();
+        assert!(matches!(err, ContractError::PollNotOpen {}));
     }
-    // Following code omitted
 
     #[test]
-    fn test_instantiate() {
-        //eprintln!("{} ÂSSSASAS", Addr::unchecked(ADDR1));
+    fn test_query_results() {
         let mut deps = mock_dependencies();
-        //Mock the dependencies
+        let mut env = mock_env();
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
 
-        let env = mock_env();
-        //MOck the contract environment
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+        create_policy_poll(
+            deps.as_mut(),
+            env.clone(),
+            "poll",
+            VoteChangePolicy::Changeable,
+        );
 
-        let info = mock_info(ADDR1, &vec![]);
-        //Mock the message info
+        let results = |deps: Deps, env: Env| -> ResultsResponse {
+            let msg = QueryMsg::Results {
+                poll_id: "poll".to_string(),
+            };
+            from_json(query(deps, env, msg).unwrap()).unwrap()
+        };
 
-        let msg = InstantiateMsg { admin: None };
-        //Tạo message khi muốn đặt admin là người gọi
+        // chưa có phiếu thì chưa có người thắng
+        let res = results(deps.as_ref(), env.clone());
+        assert_eq!(res.status, PollPhase::Voting);
+        assert_eq!(res.total_votes, 0);
+        assert!(res.winners.is_empty());
+        assert!(!res.tie);
+        assert_eq!(res.options[0].percentage, Decimal::zero());
 
-        ///eprintln!("hello1");
-        let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
-        //deps.as_mut() -> lấy mutable reference của DepMut.
-        //.unwrap() -> giải nén kết quả kiểu Result -> sẽ panic nếu có lỗi
+        for (voter, index) in [(ADDR1, 0), (ADDR2, 1)] {
+            let info = message_info(&Addr::unchecked(voter), &[]);
+            execute(deps.as_mut(), env.clone(), info, vote_index("poll", index)).unwrap();
+        }
+        let res = results(deps.as_ref(), env.clone());
+        assert_eq!(res.winners, vec![0, 1]);
+        assert!(res.tie);
+        assert_eq!(res.options[1].percentage, Decimal::percent(5000));
 
+        let info = message_info(&Addr::unchecked(ADDR3), &[]);
+        execute(deps.as_mut(), env.clone(), info, vote_index("poll", 1)).unwrap();
+        let res = results(deps.as_ref(), env.clone());
+        assert_eq!(res.winners, vec![1]);
+        assert!(!res.tie);
+        assert_eq!(res.total_votes, 3);
+        assert_eq!(res.unique_voters, 3);
+        assert_eq!(res.options[1].votes, 2);
         assert_eq!(
-            res.attributes,
-            vec![attr("action", "instantiate"), attr("admin", ADDR1)]
+            res.options[1].percentage,
+            Decimal::from_ratio(200u128, 3u128)
         );
-        //trong Response của Cosmwasm có trường attributes là Vec<Attribute>
+
+        // phiếu uỷ quyền được tính, hoà trở lại
+        let msg = ExecuteMsg::Delegate {
+            to: ADDR1.to_string(),
+            poll_id: None,
+        };
+        let info = message_info(&Addr::unchecked(ADDR4), &[]);
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let res = results(deps.as_ref(), env.clone());
+        assert_eq!(res.winners, vec![0, 1]);
+        assert!(res.tie);
+        assert_eq!(res.unique_voters, 4);
+
+        env.block.height += 1;
+        let msg = ExecuteMsg::FinalizePoll {
+            poll_id: "poll".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+        let res = results(deps.as_ref(), env.clone());
+        assert_eq!(res.status, PollPhase::Finalized);
+        assert_eq!(res.height, env.block.height);
+
+        // poll commit-reveal đi qua từng phase
+        create_commit_reveal_poll(deps.as_mut(), env.clone(), admin);
+        let phase = |deps: Deps, env: Env| -> PollPhase {
+            let msg = QueryMsg::Results {
+                poll_id: "secret".to_string(),
+            };
+            let res: ResultsResponse = from_json(query(deps, env, msg).unwrap()).unwrap();
+            res.status
+        };
+        assert_eq!(phase(deps.as_ref(), env.clone()), PollPhase::Voting);
+        env.block.height += 11;
+        assert_eq!(phase(deps.as_ref(), env.clone()), PollPhase::Revealing);
+        env.block.height += 10;
+        assert_eq!(phase(deps.as_ref(), env), PollPhase::Ended);
     }
 
     #[test]
-    fn test_instantiate_with_admin() {
+    fn test_query_results_at() {
         let mut deps = mock_dependencies();
-        let env = mock_env();
+        let mut env = mock_env();
+        let start = env.block.height;
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+
         let msg = InstantiateMsg {
-            admin: Some(ADDR2.to_string()),
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
         };
-        let info = mock_info(ADDR1, &vec![]);
+        let _res = instantiate(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+        create_policy_poll(
+            deps.as_mut(),
+            env.clone(),
+            "poll",
+            VoteChangePolicy::Retractable,
+        );
 
-        let res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+        let vote = |deps: DepsMut, env: Env, voter: &str, index: u32| {
+            let info = message_info(&Addr::unchecked(voter), &[]);
+            execute(deps, env, info, vote_index("poll", index)).unwrap();
+        };
+        vote(deps.as_mut(), env.clone(), ADDR1, 0);
+        vote(deps.as_mut(), env.clone(), ADDR2, 0);
+        env.block.height += 1;
+        vote(deps.as_mut(), env.clone(), ADDR2, 1);
+        vote(deps.as_mut(), env.clone(), ADDR3, 1);
+        env.block.height += 1;
+        let msg = ExecuteMsg::RetractVote {
+            poll_id: "poll".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+        env.block.height += 1;
+        let msg = ExecuteMsg::FinalizePoll {
+            poll_id: "poll".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), admin, msg).unwrap();
+
+        let results_at = |height: u64| -> ResultsAtResponse {
+            let msg = QueryMsg::ResultsAt {
+                poll_id: "poll".to_string(),
+                height,
+                start_after: None,
+                limit: None,
+            };
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap()
+        };
+        let votes = |res: &ResultsAtResponse| -> Vec<u64> {
+            res.results
+                .options
+                .iter()
+                .map(|option| option.votes)
+                .collect()
+        };
 
+        let res = results_at(start - 1);
+        assert_eq!(votes(&res), vec![0, 0]);
+        assert!(res.turnout.is_empty());
+        assert_eq!(res.results.status, PollPhase::Voting);
+
+        let res = results_at(start);
+        assert_eq!(votes(&res), vec![2, 0]);
+        assert_eq!(res.results.unique_voters, 2);
+        assert_eq!(res.results.winners, vec![0]);
+
+        let res = results_at(start + 1);
+        assert_eq!(votes(&res), vec![1, 2]);
+        assert_eq!(res.results.unique_voters, 3);
+        assert_eq!(res.results.winners, vec![1]);
+
+        let res = results_at(start + 2);
+        assert_eq!(votes(&res), vec![0, 2]);
         assert_eq!(
-            res.attributes,
-            vec![attr("action", "instantiate"), attr("admin", ADDR2)],
+            res.turnout,
+            vec![(start, 2), (start + 1, 3), (start + 2, 2)]
         );
-    }
+        assert_eq!(res.results.status, PollPhase::Voting);
 
-    #[test]
-    fn test_execute_create_poll_valid() {
-        let mut deps = mock_dependencies();
-        let env = mock_env();
-        let info = mock_info(ADDR1, &vec![]);
+        let res = results_at(start + 3);
+        assert_eq!(votes(&res), vec![0, 2]);
+        assert_eq!(res.results.status, PollPhase::Finalized);
 
-        let msg = InstantiateMsg { admin: None };
-        let res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        // height lớn nhất không bị tràn
+        let res = results_at(u64::MAX);
+        assert_eq!(votes(&res), vec![0, 2]);
 
-        let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id".to_string(),
-            question: "What's your favourite cosmos coin?".to_string(),
-            options: vec![
-                "Cosmos hub".to_string(),
-                "Juno".to_string(),
-                "Osmosis".to_string(),
-            ],
+        // phân trang turnout, số voter vẫn tính tới `height`
+        let msg = QueryMsg::ResultsAt {
+            poll_id: "poll".to_string(),
+            height: start + 2,
+            start_after: Some(start),
+            limit: Some(1),
         };
-        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let res: ResultsAtResponse =
+            from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.turnout, vec![(start + 1, 3)]);
+        assert_eq!(res.results.unique_voters, 2);
     }
 
     #[test]
-    fn test_execute_create_poll_invalid() {
+    fn test_query_stats() {
         let mut deps = mock_dependencies();
-        let env = mock_env();
-        let info = mock_info(ADDR1, &vec![]);
+        let mut env = mock_env();
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
 
-        let msg = InstantiateMsg { admin: None };
-        let res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+        let stats = |deps: Deps| -> Stats {
+            from_json(query(deps, mock_env(), QueryMsg::Stats {}).unwrap()).unwrap()
+        };
+        assert_eq!(stats(deps.as_ref()), Stats::default());
 
-        let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id".to_string(),
-            question: "What's your favourite number?".to_string(),
-            options: vec![
-                "1".to_string(),
-                "2".to_string(),
-                "3".to_string(),
-                "4".to_string(),
-                "5".to_string(),
-                "6".to_string(),
-                "7".to_string(),
-                "8".to_string(),
-                "9".to_string(),
-                "10".to_string(),
-                "11".to_string(),
-            ],
+        for poll_id in ["a", "b", "c"] {
+            create_policy_poll(
+                deps.as_mut(),
+                env.clone(),
+                poll_id,
+                VoteChangePolicy::Retractable,
+            );
+        }
+        let vote = |deps: DepsMut, voter: &str, poll_id: &str, index: u32| {
+            let info = message_info(&Addr::unchecked(voter), &[]);
+            execute(deps, mock_env(), info, vote_index(poll_id, index)).unwrap();
         };
+        vote(deps.as_mut(), ADDR1, "a", 0);
+        vote(deps.as_mut(), ADDR2, "b", 0);
+        vote(deps.as_mut(), ADDR3, "b", 1);
+        vote(deps.as_mut(), ADDR1, "b", 1);
+        // đổi phiếu không tính thêm ballot
+        vote(deps.as_mut(), ADDR1, "b", 0);
 
-        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        //unwrap_err(): lấy giá trị lỗi E từ Result<T,E> -> nếu kết quả trả về Ok(T) -> panic
+        let res = stats(deps.as_ref());
+        assert_eq!(res.total_polls, 3);
+        assert_eq!(res.open_polls, 3);
+        assert_eq!(res.total_ballots, 4);
+        assert_eq!(res.unique_voters, 3);
+        assert_eq!(
+            res.top_polls,
+            vec![("b".to_string(), 3), ("a".to_string(), 1)]
+        );
+
+        // rút phiếu làm giảm turnout
+        let msg = ExecuteMsg::RetractVote {
+            poll_id: "b".to_string(),
+        };
+        let info = message_info(&Addr::unchecked(ADDR2), &[]);
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        env.block.height += 1;
+        let msg = ExecuteMsg::FinalizePoll {
+            poll_id: "a".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+        let msg = ExecuteMsg::CancelPoll {
+            poll_id: "c".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
+        let msg = ExecuteMsg::DeletePoll {
+            poll_id: "a".to_string(),
+        };
+        execute(deps.as_mut(), env, admin, msg).unwrap();
+
+        let res = stats(deps.as_ref());
+        assert_eq!(res.total_polls, 3);
+        assert_eq!(res.open_polls, 1);
+        assert_eq!(res.total_ballots, 3);
+        assert_eq!(res.unique_voters, 3);
+        assert_eq!(res.top_polls, vec![("b".to_string(), 2)]);
     }
 
     #[test]
-    fn test_execute_vote_valid() {
+    fn test_query_user_overview() {
         let mut deps = mock_dependencies();
-        let env = mock_env();
-        let info = mock_info(ADDR1, &vec![]);
+        let mut env = mock_env();
+        let admin = message_info(&Addr::unchecked(ADDR1), &[]);
+        let user = message_info(&Addr::unchecked(ADDR2), &[]);
 
-        let msg = InstantiateMsg { admin: None };
-        let res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: Some(coin(10, "ucosm")),
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), admin.clone(), msg).unwrap();
 
-        //tạo poll
-        let msg = ExecuteMsg::CreatePoll {
-            poll_id: "some_id".to_string(),
-            question: "What's your favourite Cosmos coin?".to_string(),
-            options: vec![
-                "Cosmos Hub".to_string(),
-                "Juno".to_string(),
-                "Osmosis".to_string(),
-            ],
+        // ADDR2 tạo poll, ADDR1 tạo poll có quỹ thưởng 100ucosm
+        for (poll_id, creator, funds, reward_split) in [
+            ("mine", ADDR2, 10, None),
+            ("rewarded", ADDR1, 110, Some(RewardSplit::Equal)),
+        ] {
+            let msg = ExecuteMsg::CreatePoll {
+                poll_id: poll_id.to_string(),
+                question: "What's your favourite Cosmos coin?".to_string(),
+                options: vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+                voting_mode: None,
+                reward_split,
+                market: None,
+                eligibility: None,
+                metadata: None,
+                option_metadata: None,
+                vote_change: None,
+                duration: None,
+            };
+            let info = message_info(&Addr::unchecked(creator), &[coin(funds, "ucosm")]);
+            execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        }
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            user.clone(),
+            vote_index("mine", 1),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            user.clone(),
+            vote_index("rewarded", 0),
+        )
+        .unwrap();
+        let msg = ExecuteMsg::Delegate {
+            to: ADDR3.to_string(),
+            poll_id: None,
         };
-        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        execute(deps.as_mut(), env.clone(), user.clone(), msg).unwrap();
+        let msg = ExecuteMsg::Delegate {
+            to: ADDR4.to_string(),
+            poll_id: Some("mine".to_string()),
+        };
+        execute(deps.as_mut(), env.clone(), user, msg).unwrap();
 
-        //vote lần đầu
-        let msg = ExecuteMsg::Vote {
-            poll_id: "some_id".to_string(),
-            vote: "Juno".to_string(),
+        env.block.height += 1;
+        let msg = ExecuteMsg::FinalizePoll {
+            poll_id: "rewarded".to_string(),
         };
-        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        execute(deps.as_mut(), env.clone(), admin, msg).unwrap();
 
-        //đổi vote
-        let msg = ExecuteMsg::Vote {
-            poll_id: "some_id".to_string(),
-            vote: "Osmosis".to_string(),
+        // (created_after, voted_after, delegations_after)
+        let page = |deps: Deps, cursors: [Option<&str>; 3], limit: Option<u32>| {
+            let [created_after, voted_after, delegations_after] =
+                cursors.map(|cursor| cursor.map(str::to_string));
+            let msg = QueryMsg::UserOverview {
+                address: ADDR2.to_string(),
+                created_after,
+                voted_after,
+                delegations_after,
+                limit,
+            };
+            from_json::<UserOverviewResponse>(query(deps, env.clone(), msg).unwrap()).unwrap()
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let overview = |deps: Deps, limit: Option<u32>| page(deps, [None, None, None], limit);
+        let res = overview(deps.as_ref(), None);
+        assert_eq!(res.created.len(), 1);
+        assert_eq!(res.created[0].0, "mine");
+        assert_eq!(res.deposits, vec![("mine".to_string(), coin(10, "ucosm"))]);
+        assert_eq!(res.voted.len(), 2);
+        assert_eq!(res.voted[0].poll_id, "mine");
+        assert_eq!(res.voted[0].ballot.option, 1);
+        assert_eq!(res.voted[0].status, PollPhase::Voting);
+        assert_eq!(res.voted[1].poll_id, "rewarded");
+        assert_eq!(res.voted[1].status, PollPhase::Finalized);
+        assert_eq!(
+            res.rewards,
+            vec![("rewarded".to_string(), coin(100, "ucosm"))]
+        );
+        assert!(res.payouts.is_empty());
+        assert_eq!(
+            res.delegations,
+            vec![
+                (GLOBAL_SCOPE.to_string(), Addr::unchecked(ADDR3)),
+                ("mine".to_string(), Addr::unchecked(ADDR4)),
+            ]
+        );
+
+        // trang đầu: mỗi danh sách một phần tử
+        let res = overview(deps.as_ref(), Some(1));
+        assert_eq!(res.created[0].0, "mine");
+        assert_eq!(res.voted.len(), 1);
+        assert_eq!(res.voted[0].poll_id, "mine");
+        assert!(res.rewards.is_empty());
+        assert_eq!(res.delegations[0].0, GLOBAL_SCOPE);
+
+        // trang sau: mỗi danh sách đi tiếp từ cursor của nó
+        let res = page(
+            deps.as_ref(),
+            [Some("mine"), Some("mine"), Some(GLOBAL_SCOPE)],
+            Some(1),
+        );
+        assert!(res.created.is_empty());
+        assert!(res.deposits.is_empty());
+        assert_eq!(res.voted.len(), 1);
+        assert_eq!(res.voted[0].poll_id, "rewarded");
+        assert_eq!(res.rewards.len(), 1);
+        assert_eq!(
+            res.delegations,
+            vec![("mine".to_string(), Addr::unchecked(ADDR4))]
+        );
+
+        // trang cuối
+        let res = page(
+            deps.as_ref(),
+            [Some("mine"), Some("rewarded"), Some("mine")],
+            Some(1),
+        );
+        assert!(res.created.is_empty());
+        assert!(res.voted.is_empty());
+        assert!(res.rewards.is_empty());
+        assert!(res.delegations.is_empty());
+
+        // claim xong thì không còn pending
+        let msg = ExecuteMsg::ClaimReward {
+            poll_id: "rewarded".to_string(),
+        };
+        let info = message_info(&Addr::unchecked(ADDR2), &[]);
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert!(overview(deps.as_ref(), None).rewards.is_empty());
     }
 
     #[test]
-    fn test_execute_vote_invalid() {
+    fn test_migrate() {
         let mut deps = mock_dependencies();
         let env = mock_env();
-        let info = mock_info(ADDR1, &vec![]);
+        let info = message_info(&Addr::unchecked(ADDR1), &[]);
+
+        let msg = InstantiateMsg {
+            admin: None,
+            poll_deposit: None,
+            community_pool: None,
+        };
+        let _res = instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let res = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+        assert_eq!(res.attributes[0], attr("action", "migrate"));
 
-        let msg = InstantiateMsg { admin: None };
-        let res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        // không hạ version
+        cw2::set_contract_version(deps.as_mut().storage, "crates.io:cw-starter", "99.0.0").unwrap();
+        let err = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidMigration { .. }));
 
-        let msg = ExecuteMsg::Vote {
-            // tạo vote nhưng poll_id chưa có
+        // không migrate từ contract khác
+        cw2::set_contract_version(deps.as_mut().storage, "crates.io:other", "0.1.0").unwrap();
+        let err = migrate(deps.as_mut(), env, MigrateMsg {}).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidMigration { .. }));
+    }
+
+    #[test]
+    fn test_migrate_legacy_state() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+
+        // state do bản 0.1.0 ghi: config chỉ có admin, options nằm trong poll, ballot lưu label
+        cw2::set_contract_version(deps.as_mut().storage, "crates.io:cw-starter", "0.1.0").unwrap();
+        let storage = deps.as_mut().storage;
+        LEGACY_CONFIG
+            .save(
+                storage,
+                &LegacyConfig {
+                    admin: Addr::unchecked(ADDR1),
+                },
+            )
+            .unwrap();
+        LEGACY_POLLS
+            .save(
+                storage,
+                "some_id".to_string(),
+                &LegacyPoll {
+                    creator: Addr::unchecked(ADDR1),
+                    question: "What's your favourite Cosmos coin?".to_string(),
+                    options: vec![
+                        ("Cosmos Hub".to_string(), 0),
+                        ("Juno".to_string(), 2),
+                        ("Osmosis".to_string(), 0),
+                    ],
+                },
+            )
+            .unwrap();
+        for voter in [ADDR2, ADDR3] {
+            LEGACY_BALLOTS
+                .save(
+                    storage,
+                    (Addr::unchecked(voter), "some_id".to_string()),
+                    &LegacyBallot {
+                        option: "Juno".to_string(),
+                    },
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            storage.get(b"config"),
+            Some(format!(r#"{{"admin":"{}"}}"#, ADDR1).into_bytes())
+        );
+
+        let res = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+        assert_eq!(res.attributes[1], attr("from_version", "0.1.0"));
+
+        let msg = QueryMsg::Config {};
+        let res: Config = from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.admin, Addr::unchecked(ADDR1));
+        assert_eq!(res.community_pool, Addr::unchecked(ADDR1));
+
+        let msg = QueryMsg::Poll {
             poll_id: "some_id".to_string(),
-            vote: "Juno".to_string(),
         };
+        let res: PollResponse = from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        let poll = res.poll.unwrap();
+        assert_eq!(poll.option_count, 3);
+        assert_eq!(poll.status, PollStatus::Open);
+        assert_eq!(
+            res.options
+                .iter()
+                .map(|(option, votes)| (option.label.as_str(), *votes))
+                .collect::<Vec<_>>(),
+            vec![("Cosmos Hub", 0), ("Juno", 2), ("Osmosis", 0)]
+        );
 
-        //test error
-        let _err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
-        eprintln!("invalid vote {}", _err);
-
-        //tạo poll
-        let msg = ExecuteMsg::CreatePoll {
+        let msg = QueryMsg::Vote {
             poll_id: "some_id".to_string(),
-            question: "What's your favourite Cosmos coin?".to_string(),
-            options: vec![
-                "Cosmos Hub".to_string(),
-                "Juno".to_string(),
-                "Osmosis".to_string(),
-            ],
+            address: ADDR2.to_string(),
         };
-        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let res: VoteResponse = from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.vote.unwrap().option, 1);
 
-        //tạo vote không hợp lệ
-        let msg = ExecuteMsg::Vote {
+        let res: Stats =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::Stats {}).unwrap()).unwrap();
+        assert_eq!(res.total_polls, 1);
+        assert_eq!(res.total_ballots, 2);
+        assert_eq!(res.unique_voters, 2);
+        assert_eq!(res.top_polls, vec![("some_id".to_string(), 2)]);
+
+        // ballot cũ đổi phiếu được như ballot mới
+        env.block.height += 1;
+        let info = message_info(&Addr::unchecked(ADDR2), &[]);
+        let _res = execute(deps.as_mut(), env.clone(), info, vote_index("some_id", 2)).unwrap();
+        let msg = QueryMsg::Poll {
             poll_id: "some_id".to_string(),
-            vote: "DVPN".to_string(),
         };
-        let _err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        let res: PollResponse = from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.options[1].1, 1);
+        assert_eq!(res.options[2].1, 1);
+
+        // chạy lại migrate trên state mới không ghi đè gì
+        let _res = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+        let res: Stats = from_json(query(deps.as_ref(), env, QueryMsg::Stats {}).unwrap()).unwrap();
+        assert_eq!(res.total_polls, 1);
+    }
+
+    #[test]
+    fn test_query_response_schemas() {
+        // mỗi variant của QueryMsg đều có schema response cho codegen
+        let schemas = QueryMsg::response_schemas().unwrap();
+        assert_eq!(schemas.len(), 29);
+        assert!(schemas.contains_key("results"));
+        assert!(schemas.contains_key("user_overview"));
+        assert_eq!(
+            schemas["config"].schema.metadata.as_ref().unwrap().title,
+            Some("Config".to_string())
+        );
     }
 }
 
@@ -7,6 +7,8 @@ use cosmwasm_std::StdError; //enum chứa các lỗi chuẩn của cosmwasm
                             //     Overflow { .. },                    // Lỗi tràn số
                             // }
 
+use cosmwasm_std::VerificationError;
+
 use thiserror::Error; // crate giúp tạo enum lỗi tùy chỉnh
 
 #[derive(Error, Debug)]
@@ -14,11 +16,150 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Verification(#[from] VerificationError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
     #[error("Too many poll options")]
     TooManyOptions {},
+
+    #[error("Invalid commit/reveal phase heights")]
+    InvalidPhases {},
+
+    #[error("Operation not supported by this poll's voting mode")]
+    WrongVotingMode {},
+
+    #[error("Commit phase has ended")]
+    CommitPhaseClosed {},
+
+    #[error("Poll is not in its reveal phase")]
+    NotInRevealPhase {},
+
+    #[error("No commitment found for this voter")]
+    NoCommitment {},
+
+    #[error("Vote has already been revealed")]
+    AlreadyRevealed {},
+
+    #[error("Revealed vote does not match commitment")]
+    CommitmentMismatch {},
+
+    #[error("Invalid poll id")]
+    InvalidPollId {},
+
+    #[error("Delegation would create a cycle")]
+    DelegationCycle {},
+
+    #[error("No delegation to remove")]
+    NoDelegation {},
+
+    #[error("Invalid signature")]
+    InvalidSignature {},
+
+    #[error("Invalid nonce: expected {expected}")]
+    InvalidNonce { expected: u64 },
+
+    #[error("Poll {poll_id} already exists")]
+    PollAlreadyExists { poll_id: String },
+
+    #[error("Poll creation requires a deposit of {expected}")]
+    InvalidDeposit { expected: String },
+
+    #[error("Poll is not open")]
+    PollNotOpen {},
+
+    #[error("Poll has not ended yet")]
+    PollNotEnded {},
+
+    #[error("Reward pool must be a single non-zero native coin")]
+    InvalidRewardPool {},
+
+    #[error("No reward to claim")]
+    NoReward {},
+
+    #[error("Reward already claimed")]
+    AlreadyClaimed {},
+
+    #[error("Stake must be a single non-zero coin of {denom}")]
+    InvalidStake { denom: String },
+
+    #[error("Staked vote cannot be moved to another option")]
+    StakeLocked {},
+
+    #[error("Outcome is not an option of this poll")]
+    InvalidOutcome {},
+
+    #[error("No payout to claim")]
+    NoPayout {},
+
+    #[error("Fee must be between 0 and 1")]
+    InvalidFee {},
+
+    #[error("Contract is paused")]
+    Paused {},
+
+    #[error("Address is not eligible to vote in this poll")]
+    NotEligible {},
+
+    #[error("Poll already reported by this address")]
+    AlreadyReported {},
+
+    #[error("Report reason is too long")]
+    ReasonTooLong {},
+
+    #[error("Metadata field {field} is too long")]
+    MetadataTooLong { field: String },
+
+    #[error("Too many tags or option metadata does not match options")]
+    InvalidMetadata {},
+
+    #[error("Poll already has votes and can no longer be edited")]
+    PollHasVotes {},
+
+    #[error("Poll has not been deleted")]
+    PollNotDeleted {},
+
+    #[error("Ballots are still needed to claim market payouts")]
+    PayoutsPending {},
+
+    #[error("Category is not in the configured list")]
+    InvalidCategory {},
+
+    #[error("Template {template_id} not found")]
+    TemplateNotFound { template_id: String },
+
+    #[error("Option index {index} is out of range, poll has {count} options")]
+    InvalidOptionIndex { index: u32, count: u32 },
+
+    #[error("Series period has not elapsed yet, next advance at height {next_height}")]
+    SeriesNotDue { next_height: u64 },
+
+    #[error("Poll {poll_id} not found")]
+    PollNotFound { poll_id: String },
+
+    #[error("Option {option} is not an option of this poll")]
+    InvalidOption { option: String },
+
+    #[error("Already voted for this option")]
+    AlreadyVoted {},
+
+    #[error("This poll does not allow changing the vote")]
+    VoteChangeNotAllowed {},
+
+    #[error("No ballot to retract")]
+    NoBallot {},
+
+    #[error("Unexpected funds sent")]
+    UnexpectedFunds {},
+    #[error("Reward weights are still being settled")]
+    RewardsNotSettled {},
+
+    #[error("Limit must be greater than zero")]
+    InvalidLimit {},
+    #[error("Cannot migrate from {contract} {version}")]
+    InvalidMigration { contract: String, version: String },
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
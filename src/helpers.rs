@@ -1,12 +1,29 @@
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-use cosmwasm_std::{to_json_binary, Addr, CosmosMsg, StdResult, WasmMsg};
+use cosmwasm_std::{
+    to_json_binary, to_json_vec, Addr, Binary, Coin, CosmosMsg, Decimal, QuerierWrapper, StdResult,
+    WasmMsg,
+};
+use sha2::{Digest, Sha256};
 
-use crate::msg::ExecuteMsg;
+use crate::msg::{
+    AllPollsResponse, ExecuteMsg, InstantiateMsg, MarketParams, MigrateMsg, PollResponse, QueryMsg,
+    ResultsResponse, SignedVote, VoteOption, VoteResponse,
+};
+use crate::state::{
+    Config, Eligibility, OptionMetadata, PauseScope, PollMetadata, RewardSplit, Role, Template,
+    VoteChangePolicy, VotingMode,
+};
 
 /// CwTemplateContract is a wrapper around Addr that provides a lot of helpers
 /// for working with this.
+///
+/// The `*_msg` methods build a `CosmosMsg` for one `ExecuteMsg` variant each.
+/// Variants the contract accepts funds for (poll deposit, reward pool,
+/// market stake, series bounty) take a `funds` argument; anything else can
+/// still be sent with funds through [`CwTemplateContract::call_with_funds`].
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct CwTemplateContract(pub Addr);
 
@@ -16,12 +33,544 @@ impl CwTemplateContract {
     }
 
     pub fn call<T: Into<ExecuteMsg>>(&self, msg: T) -> StdResult<CosmosMsg> {
+        self.call_with_funds(msg, vec![])
+    }
+
+    pub fn call_with_funds<T: Into<ExecuteMsg>>(
+        &self,
+        msg: T,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
         let msg = to_json_binary(&msg.into())?;
         Ok(WasmMsg::Execute {
             contract_addr: self.addr().into(),
             msg,
-            funds: vec![],
+            funds,
+        }
+        .into())
+    }
+
+    /// Instantiates a new poll contract from `code_id`.
+    pub fn instantiate_msg(
+        code_id: u64,
+        msg: &InstantiateMsg,
+        admin: Option<String>,
+        label: impl Into<String>,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Instantiate {
+            admin,
+            code_id,
+            msg: to_json_binary(msg)?,
+            funds,
+            label: label.into(),
+        }
+        .into())
+    }
+
+    /// Migrates this contract to `new_code_id`, must be sent by the wasm admin.
+    pub fn migrate_msg(&self, new_code_id: u64, msg: &MigrateMsg) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Migrate {
+            contract_addr: self.addr().into(),
+            new_code_id,
+            msg: to_json_binary(msg)?,
         }
         .into())
     }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_poll_msg(
+        &self,
+        poll_id: impl Into<String>,
+        question: impl Into<String>,
+        options: Vec<String>,
+        voting_mode: Option<VotingMode>,
+        reward_split: Option<RewardSplit>,
+        market: Option<MarketParams>,
+        eligibility: Option<Eligibility>,
+        metadata: Option<PollMetadata>,
+        option_metadata: Option<Vec<OptionMetadata>>,
+        vote_change: Option<VoteChangePolicy>,
+        duration: Option<u64>,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        self.call_with_funds(
+            ExecuteMsg::CreatePoll {
+                poll_id: poll_id.into(),
+                question: question.into(),
+                options,
+                voting_mode,
+                reward_split,
+                market,
+                eligibility,
+                metadata,
+                option_metadata,
+                vote_change,
+                duration,
+            },
+            funds,
+        )
+    }
+
+    pub fn create_poll_from_template_msg(
+        &self,
+        template_id: impl Into<String>,
+        poll_id: impl Into<String>,
+        params: BTreeMap<String, String>,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        self.call_with_funds(
+            ExecuteMsg::CreatePollFromTemplate {
+                template_id: template_id.into(),
+                poll_id: poll_id.into(),
+                params,
+            },
+            funds,
+        )
+    }
+
+    pub fn save_template_msg(
+        &self,
+        template_id: impl Into<String>,
+        template: Template,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::SaveTemplate {
+            template_id: template_id.into(),
+            template,
+        })
+    }
+
+    pub fn remove_template_msg(&self, template_id: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::RemoveTemplate {
+            template_id: template_id.into(),
+        })
+    }
+
+    /// `funds` prefunds the crank bounty when `bounty` is set.
+    pub fn create_series_msg(
+        &self,
+        series_id: impl Into<String>,
+        template_id: impl Into<String>,
+        params: BTreeMap<String, String>,
+        period: u64,
+        bounty: Option<Coin>,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        self.call_with_funds(
+            ExecuteMsg::CreateSeries {
+                series_id: series_id.into(),
+                template_id: template_id.into(),
+                params,
+                period,
+                bounty,
+            },
+            funds,
+        )
+    }
+
+    pub fn advance_series_msg(&self, series_id: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::AdvanceSeries {
+            series_id: series_id.into(),
+        })
+    }
+
+    pub fn fund_series_msg(
+        &self,
+        series_id: impl Into<String>,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        self.call_with_funds(
+            ExecuteMsg::FundSeries {
+                series_id: series_id.into(),
+            },
+            funds,
+        )
+    }
+
+    pub fn stop_series_msg(&self, series_id: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::StopSeries {
+            series_id: series_id.into(),
+        })
+    }
+
+    pub fn update_poll_msg(
+        &self,
+        poll_id: impl Into<String>,
+        question: Option<String>,
+        options: Option<Vec<String>>,
+        metadata: Option<PollMetadata>,
+        option_metadata: Option<Vec<OptionMetadata>>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::UpdatePoll {
+            poll_id: poll_id.into(),
+            question,
+            options,
+            metadata,
+            option_metadata,
+        })
+    }
+
+    /// `funds` is the stake when the poll is a prediction market.
+    pub fn vote_msg(
+        &self,
+        poll_id: impl Into<String>,
+        vote: VoteOption,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        self.call_with_funds(
+            ExecuteMsg::Vote {
+                poll_id: poll_id.into(),
+                vote,
+            },
+            funds,
+        )
+    }
+
+    pub fn commit_vote_msg(
+        &self,
+        poll_id: impl Into<String>,
+        commitment: Binary,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::CommitVote {
+            poll_id: poll_id.into(),
+            commitment,
+        })
+    }
+
+    pub fn retract_vote_msg(&self, poll_id: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::RetractVote {
+            poll_id: poll_id.into(),
+        })
+    }
+
+    pub fn reveal_vote_msg(
+        &self,
+        poll_id: impl Into<String>,
+        vote: VoteOption,
+        salt: impl Into<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::RevealVote {
+            poll_id: poll_id.into(),
+            vote,
+            salt: salt.into(),
+        })
+    }
+
+    pub fn delegate_msg(
+        &self,
+        to: impl Into<String>,
+        poll_id: Option<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Delegate {
+            to: to.into(),
+            poll_id,
+        })
+    }
+
+    pub fn undelegate_msg(&self, poll_id: Option<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Undelegate { poll_id })
+    }
+
+    pub fn submit_signed_votes_msg(&self, votes: Vec<SignedVote>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::SubmitSignedVotes { votes })
+    }
+
+    pub fn finalize_poll_msg(&self, poll_id: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::FinalizePoll {
+            poll_id: poll_id.into(),
+        })
+    }
+
+    pub fn cancel_poll_msg(&self, poll_id: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::CancelPoll {
+            poll_id: poll_id.into(),
+        })
+    }
+
+    pub fn hide_poll_msg(&self, poll_id: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::HidePoll {
+            poll_id: poll_id.into(),
+        })
+    }
+
+    pub fn unhide_poll_msg(&self, poll_id: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::UnhidePoll {
+            poll_id: poll_id.into(),
+        })
+    }
+
+    pub fn report_poll_msg(
+        &self,
+        poll_id: impl Into<String>,
+        reason: impl Into<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ReportPoll {
+            poll_id: poll_id.into(),
+            reason: reason.into(),
+        })
+    }
+
+    pub fn delete_poll_msg(&self, poll_id: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::DeletePoll {
+            poll_id: poll_id.into(),
+        })
+    }
+
+    pub fn prune_ballots_msg(
+        &self,
+        poll_id: impl Into<String>,
+        limit: Option<u32>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::PruneBallots {
+            poll_id: poll_id.into(),
+            limit,
+        })
+    }
+
+    pub fn settle_rewards_msg(
+        &self,
+        poll_id: impl Into<String>,
+        limit: Option<u32>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::SettleRewards {
+            poll_id: poll_id.into(),
+            limit,
+        })
+    }
+
+    pub fn claim_reward_msg(&self, poll_id: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ClaimReward {
+            poll_id: poll_id.into(),
+        })
+    }
+
+    pub fn resolve_poll_msg(
+        &self,
+        poll_id: impl Into<String>,
+        outcome: impl Into<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ResolvePoll {
+            poll_id: poll_id.into(),
+            outcome: outcome.into(),
+        })
+    }
+
+    pub fn void_poll_msg(&self, poll_id: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::VoidPoll {
+            poll_id: poll_id.into(),
+        })
+    }
+
+    pub fn claim_payout_msg(&self, poll_id: impl Into<String>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::ClaimPayout {
+            poll_id: poll_id.into(),
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_config_msg(
+        &self,
+        admin: Option<String>,
+        poll_deposit: Option<Coin>,
+        community_pool: Option<String>,
+        market_fee: Option<Decimal>,
+        restricted_creation: Option<bool>,
+        report_threshold: Option<u32>,
+        categories: Option<Vec<String>>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::UpdateConfig {
+            admin,
+            poll_deposit,
+            community_pool,
+            market_fee,
+            restricted_creation,
+            report_threshold,
+            categories,
+        })
+    }
+
+    pub fn pause_msg(
+        &self,
+        scopes: Option<Vec<PauseScope>>,
+        until_height: Option<u64>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Pause {
+            scopes,
+            until_height,
+        })
+    }
+
+    pub fn unpause_msg(&self) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::Unpause {})
+    }
+
+    pub fn grant_role_msg(&self, address: impl Into<String>, role: Role) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::GrantRole {
+            address: address.into(),
+            role,
+        })
+    }
+
+    pub fn revoke_role_msg(&self, address: impl Into<String>, role: Role) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::RevokeRole {
+            address: address.into(),
+            role,
+        })
+    }
+
+    pub fn update_electorate_msg(
+        &self,
+        add: Vec<String>,
+        remove: Vec<String>,
+    ) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::UpdateElectorate { add, remove })
+    }
+
+    /// Runs any `QueryMsg` against this contract and decodes the response.
+    pub fn query<T: DeserializeOwned>(
+        &self,
+        querier: &QuerierWrapper,
+        msg: &QueryMsg,
+    ) -> StdResult<T> {
+        querier.query_wasm_smart(self.addr(), msg)
+    }
+
+    pub fn poll(
+        &self,
+        querier: &QuerierWrapper,
+        poll_id: impl Into<String>,
+    ) -> StdResult<PollResponse> {
+        self.query(
+            querier,
+            &QueryMsg::Poll {
+                poll_id: poll_id.into(),
+            },
+        )
+    }
+
+    pub fn list_polls(
+        &self,
+        querier: &QuerierWrapper,
+        include_hidden: Option<bool>,
+    ) -> StdResult<AllPollsResponse> {
+        self.query(querier, &QueryMsg::AllPoll { include_hidden })
+    }
+
+    pub fn vote(
+        &self,
+        querier: &QuerierWrapper,
+        poll_id: impl Into<String>,
+        address: impl Into<String>,
+    ) -> StdResult<VoteResponse> {
+        self.query(
+            querier,
+            &QueryMsg::Vote {
+                poll_id: poll_id.into(),
+                address: address.into(),
+            },
+        )
+    }
+
+    pub fn results(
+        &self,
+        querier: &QuerierWrapper,
+        poll_id: impl Into<String>,
+    ) -> StdResult<ResultsResponse> {
+        self.query(
+            querier,
+            &QueryMsg::Results {
+                poll_id: poll_id.into(),
+            },
+        )
+    }
+
+    pub fn config(&self, querier: &QuerierWrapper) -> StdResult<Config> {
+        self.query(querier, &QueryMsg::Config {})
+    }
+}
+
+/// Commitment used by commit-reveal polls:
+/// sha256(option index as u32 BE || salt length as u64 BE || salt || voter).
+/// Clients compute this off-chain and send it with `ExecuteMsg::CommitVote`.
+pub fn compute_commitment(option: u32, salt: &str, voter: &Addr) -> Binary {
+    let mut hasher = Sha256::new();
+    // index cố định 4 byte, salt có tiền tố độ dài nên không thể ghép lệch ranh giới
+    hasher.update(option.to_be_bytes());
+    hasher.update((salt.len() as u64).to_be_bytes());
+    hasher.update(salt.as_bytes());
+    hasher.update(voter.as_bytes());
+    Binary::from(hasher.finalize().to_vec())
+}
+
+// Các struct dưới đây dựng sign doc ADR-036 (amino JSON), field phải theo thứ tự alphabet
+#[derive(Serialize)]
+struct SignDoc {
+    account_number: String,
+    chain_id: String,
+    fee: SignDocFee,
+    memo: String,
+    msgs: Vec<SignDocMsg>,
+    sequence: String,
+}
+
+#[derive(Serialize)]
+struct SignDocFee {
+    amount: Vec<String>,
+    gas: String,
+}
+
+#[derive(Serialize)]
+struct SignDocMsg {
+    #[serde(rename = "type")]
+    msg_type: String,
+    value: MsgSignData,
+}
+
+#[derive(Serialize)]
+struct MsgSignData {
+    data: Binary,
+    signer: String,
+}
+
+#[derive(Serialize)]
+struct VotePayload<'a> {
+    contract: &'a str,
+    nonce: u64,
+    poll_id: &'a str,
+    vote: &'a str,
+}
+
+/// ADR-036 sign doc bytes a voter signs (sha256 + secp256k1) for
+/// `ExecuteMsg::SubmitSignedVotes`. The signed data binds the ballot to this
+/// contract and to the voter's current nonce.
+pub fn signed_vote_sign_doc(
+    contract: &Addr,
+    signer: &Addr,
+    poll_id: &str,
+    vote: &str,
+    nonce: u64,
+) -> StdResult<Vec<u8>> {
+    let data = to_json_vec(&VotePayload {
+        contract: contract.as_str(),
+        nonce,
+        poll_id,
+        vote,
+    })?;
+    to_json_vec(&SignDoc {
+        account_number: "0".to_string(),
+        chain_id: "".to_string(),
+        fee: SignDocFee {
+            amount: vec![],
+            gas: "0".to_string(),
+        },
+        memo: "".to_string(),
+        msgs: vec![SignDocMsg {
+            msg_type: "sign/MsgSignData".to_string(),
+            value: MsgSignData {
+                data: Binary::from(data),
+                signer: signer.to_string(),
+            },
+        }],
+        sequence: "0".to_string(),
+    })
 }
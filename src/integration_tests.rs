@@ -0,0 +1,109 @@
+use cosmwasm_std::{coin, Addr, Empty, WasmMsg};
+use cw_multi_test::{App, AppResponse, Contract, ContractWrapper, Executor};
+
+use crate::contract::{execute, instantiate, migrate, query};
+use crate::helpers::CwTemplateContract;
+use crate::msg::{InstantiateMsg, MigrateMsg, PollPhase, VoteOption};
+
+fn poll_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(execute, instantiate, query).with_migrate(migrate))
+}
+
+fn contract_address(res: &AppResponse) -> Addr {
+    let attr = res
+        .events
+        .iter()
+        .flat_map(|event| &event.attributes)
+        .find(|attr| attr.key == "_contract_address")
+        .unwrap();
+    Addr::unchecked(&attr.value)
+}
+
+#[test]
+fn test_typed_client() {
+    let mut app = App::default();
+    let creator = app.api().addr_make("creator");
+    let voter = app.api().addr_make("voter");
+    app.init_modules(|router, _, storage| {
+        router
+            .bank
+            .init_balance(storage, &creator, vec![coin(100, "ucosm")])
+            .unwrap()
+    });
+    let code_id = app.store_code(poll_contract());
+
+    let msg = CwTemplateContract::instantiate_msg(
+        code_id,
+        &InstantiateMsg {
+            admin: None,
+            poll_deposit: Some(coin(10, "ucosm")),
+            community_pool: None,
+        },
+        Some(creator.to_string()),
+        "polls",
+        vec![],
+    )
+    .unwrap();
+    let res = app.execute(creator.clone(), msg).unwrap();
+    let contract = CwTemplateContract(contract_address(&res));
+
+    let querier = app.wrap();
+    let config = contract.config(&querier).unwrap();
+    assert_eq!(config.admin, creator);
+    assert_eq!(config.poll_deposit, Some(coin(10, "ucosm")));
+
+    // tiền cọc gửi kèm qua builder
+    let msg = contract
+        .create_poll_msg(
+            "poll",
+            "What's your favourite Cosmos coin?",
+            vec!["Cosmos Hub".to_string(), "Juno".to_string()],
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            vec![coin(10, "ucosm")],
+        )
+        .unwrap();
+    app.execute(creator.clone(), msg).unwrap();
+    let msg = contract
+        .vote_msg("poll", VoteOption::Label("Juno".to_string()), vec![])
+        .unwrap();
+    app.execute(voter.clone(), msg).unwrap();
+
+    let querier = app.wrap();
+    let res = contract.poll(&querier, "poll").unwrap();
+    assert_eq!(res.poll.unwrap().creator, creator);
+    assert_eq!(res.options[1].1, 1);
+    assert_eq!(contract.list_polls(&querier, None).unwrap().polls.len(), 1);
+    let res = contract.vote(&querier, "poll", voter.to_string()).unwrap();
+    assert_eq!(res.vote.unwrap().option, 1);
+    let res = contract.results(&querier, "poll").unwrap();
+    assert_eq!(res.winners, vec![1]);
+    assert_eq!(res.status, PollPhase::Voting);
+    assert_eq!(
+        querier.query_balance(contract.addr(), "ucosm").unwrap(),
+        coin(10, "ucosm")
+    );
+
+    let msg = contract.migrate_msg(code_id, &MigrateMsg {}).unwrap();
+    assert_eq!(
+        msg,
+        WasmMsg::Migrate {
+            contract_addr: contract.addr().to_string(),
+            new_code_id: code_id,
+            msg: b"{}".into(),
+        }
+        .into()
+    );
+    // admin wasm (creator) migrate sang cùng code id
+    let res = app.execute(creator, msg).unwrap();
+    assert!(res.events.iter().any(|event| event
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "action" && attr.value == "migrate")));
+}
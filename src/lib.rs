@@ -1,6 +1,8 @@
 pub mod contract;
 mod error;
 pub mod helpers;
+#[cfg(test)]
+mod integration_tests;
 pub mod msg;
 pub mod state;
 
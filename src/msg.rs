@@ -1,22 +1,15 @@
-/*kiểu template mới
-use cosmwasm_schema::{cw_serde, QueryResponses};
-
-#[cw_serde]
-pub struct InstantiateMsg {}
-
-#[cw_serde]
-pub enum ExecuteMsg {}
-
-#[cw_serde]
-#[derive(QueryResponses)]
-pub enum QueryMsg {}
-
-*/
-
-use crate::state::{Ballot, Poll};
+use crate::state::{
+    Ballot, Config, Deposit, Eligibility, Market, OptionInfo, OptionMetadata, PauseScope, Poll,
+    PollMetadata, PollResult, PollRevision, RewardPool, RewardSplit, Role, Series, SeriesInstance,
+    Stats, Template, VoteChangePolicy, VotingMode,
+};
 /// Định nghĩa các Message type của smart contract
-use schemars::JsonSchema; // tự động tạo JSON schema
-use serde::{Deserialize, Serialize};
+// cw_serde = derive Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema
+// + serde(deny_unknown_fields, rename_all = "snake_case")
+// QueryResponses: mỗi variant của QueryMsg khai báo kiểu response qua #[returns]
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Uint128};
+use std::collections::BTreeMap;
 
 //
 //Define các custom struct để gọi query
@@ -29,61 +22,566 @@ use serde::{Deserialize, Serialize};
 //     pub options: Vec<(String, u64)>,
 // }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[cw_serde]
 pub struct AllPollsResponse {
     pub polls: Vec<Poll>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+// Danh sách poll kèm poll_id để phân trang
+#[cw_serde]
+pub struct PollsResponse {
+    pub polls: Vec<(String, Poll)>,
+}
+
+#[cw_serde]
 pub struct PollResponse {
     pub poll: Option<Poll>,
+    // các option kèm số phiếu trực tiếp, theo index
+    pub options: Vec<(OptionInfo, u64)>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[cw_serde]
 pub struct VoteResponse {
     pub vote: Option<Ballot>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-#[serde(rename_all = "snake_case")] // Chuyển đổi tất cả tên field của struct/enum thành snake_case khi serialize/deserialze
+// Các commitment của một poll commit-reveal, những người chưa reveal được báo riêng
+#[cw_serde]
+pub struct CommitmentsResponse {
+    pub committed: u64,
+    pub revealed: u64,
+    pub unrevealed: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct TallyResponse {
+    pub options: Vec<(String, u64)>,
+    // số phiếu đến từ delegation (đã nằm trong options)
+    pub delegated: u64,
+}
+
+// Kết quả poll tại block được query, đã tính cả phiếu uỷ quyền
+#[cw_serde]
+pub struct ResultsResponse {
+    pub poll_id: String,
+    pub height: u64,
+    pub status: PollPhase,
+    pub options: Vec<OptionResult>,
+    pub total_votes: u64,
+    pub unique_voters: u64,
+    // index các option nhiều phiếu nhất, rỗng khi chưa có phiếu
+    pub winners: Vec<u32>,
+    // true khi có từ hai option cùng nhiều phiếu nhất
+    pub tie: bool,
+}
+
+#[cw_serde]
+pub struct ResultsAtResponse {
+    // khác Results: chỉ có phiếu trực tiếp vì delegation không được lưu theo height,
+    // unique_voters cũng không tính người uỷ quyền
+    pub results: ResultsResponse,
+    // (height, số voter) theo thứ tự height tăng dần, tối đa `limit` mục sau `start_after`
+    pub turnout: Vec<(u64, u64)>,
+}
+
+#[cw_serde]
+pub struct OptionResult {
+    pub index: u32,
+    pub label: String,
+    pub votes: u64,
+    // phần trăm trên tổng phiếu, 0 -> 100
+    pub percentage: Decimal,
+}
+
+// Trạng thái poll nhìn từ một block cụ thể
+#[cw_serde]
+pub enum PollPhase {
+    // đang nhận phiếu (hoặc commitment với poll commit-reveal)
+    Voting,
+    // poll commit-reveal trong reveal phase
+    Revealing,
+    // hết hạn vote nhưng chưa finalize
+    Ended,
+    Finalized,
+    Cancelled,
+}
+
+#[cw_serde]
+pub struct UserOverviewResponse {
+    pub created: Vec<(String, Poll)>,
+    pub voted: Vec<VotedPoll>,
+    // quỹ thưởng voter chưa claim, theo poll_id
+    pub rewards: Vec<(String, Coin)>,
+    // payout của market đã resolve hoặc stake được hoàn khi market bị void
+    pub payouts: Vec<(String, Coin)>,
+    // tiền cọc của các poll do address tạo, trả lại khi poll kết thúc
+    pub deposits: Vec<(String, Coin)>,
+    // (scope, delegate), scope rỗng là delegation cho mọi poll
+    pub delegations: Vec<(String, Addr)>,
+}
+
+#[cw_serde]
+pub struct VotedPoll {
+    pub poll_id: String,
+    pub question: String,
+    pub ballot: Ballot,
+    pub status: PollPhase,
+}
+
+#[cw_serde]
+pub struct DepositResponse {
+    pub deposit: Option<Deposit>,
+}
+
+#[cw_serde]
+pub struct DepositsResponse {
+    pub deposits: Vec<(String, Deposit)>,
+}
+
+#[cw_serde]
+pub struct RewardResponse {
+    pub pool: Option<RewardPool>,
+    // phần thưởng address còn nhận được (0 nếu chưa chốt xong weight hoặc đã nhận)
+    pub claimable: Uint128,
+    pub claimed: bool,
+}
+
+#[cw_serde]
+pub struct MarketResponse {
+    pub market: Option<Market>,
+}
+
+#[cw_serde]
+pub struct PayoutResponse {
+    pub stake: Uint128,
+    // số tiền address nhận được nếu claim bây giờ
+    pub claimable: Uint128,
+    pub claimed: bool,
+}
+
+#[cw_serde]
+pub struct NonceResponse {
+    pub nonce: u64,
+}
+
+#[cw_serde]
+pub struct TemplateResponse {
+    pub template: Option<Template>,
+}
+
+#[cw_serde]
+pub struct TemplatesResponse {
+    pub templates: Vec<(String, Template)>,
+}
+
+#[cw_serde]
+pub struct SeriesResponse {
+    pub series: Option<Series>,
+}
+
+#[cw_serde]
+pub struct SeriesHistoryResponse {
+    pub instances: Vec<SeriesInstance>,
+}
+
+#[cw_serde]
+pub struct PollResultResponse {
+    pub result: Option<PollResult>,
+}
+
+#[cw_serde]
+pub struct PollHistoryResponse {
+    pub revisions: Vec<PollRevision>,
+}
+
+#[cw_serde]
+pub struct ReportsResponse {
+    // (reporter, lý do)
+    pub reports: Vec<(Addr, String)>,
+}
+
+#[cw_serde]
+pub struct RolesResponse {
+    pub roles: Vec<Role>,
+}
+
+#[cw_serde]
+pub struct RoleMembersResponse {
+    pub members: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct ElectorateResponse {
+    pub voters: Vec<Addr>,
+}
+
+// Đồ thị delegation quanh một address trong scope được hỏi
+#[cw_serde]
+pub struct DelegationResponse {
+    // address này uỷ quyền cho ai (scope poll fallback về global)
+    pub delegate: Option<Addr>,
+    // chuỗi delegate tiếp theo cho tới người cuối cùng
+    pub chain: Vec<Addr>,
+    // những address uỷ quyền trực tiếp cho address này
+    pub delegators: Vec<Addr>,
+}
 
 // Dữ liệu init contract khi deploy lần đầu
+#[cw_serde]
 pub struct InstantiateMsg {
     pub admin: Option<String>,
+    pub poll_deposit: Option<Coin>,
+    // None -> admin
+    pub community_pool: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-#[serde(rename_all = "snake_case")]
-
 // Dữ liệu khi gọi tham thay đổi trạng thái
 // định nghĩa các hành động có thể thực hiện trên contract (các hàm public)
+#[cw_serde]
 pub enum ExecuteMsg {
     //CustomMsg {val: String};
     CreatePoll {
         poll_id: String,
         question: String,
         options: Vec<String>,
+        // None -> VotingMode::Public
+        voting_mode: Option<VotingMode>,
+        // Có giá trị -> funds gửi kèm (ngoài tiền cọc) là quỹ thưởng cho voter
+        reward_split: Option<RewardSplit>,
+        // Có giá trị -> poll là prediction market, vote phải stake token
+        market: Option<MarketParams>,
+        // None -> ai cũng vote được
+        eligibility: Option<Eligibility>,
+        metadata: Option<PollMetadata>,
+        // nếu có thì phải đủ một phần tử cho mỗi option
+        option_metadata: Option<Vec<OptionMetadata>>,
+        // None -> VoteChangePolicy::Changeable
+        vote_change: Option<VoteChangePolicy>,
+        // số block poll công khai mở để vote, None -> tới khi finalize
+        duration: Option<u64>,
+    },
+    // Tạo poll từ template, "{key}" trong template được thay bằng params[key]
+    CreatePollFromTemplate {
+        template_id: String,
+        poll_id: String,
+        params: BTreeMap<String, String>,
+    },
+    // Owner thêm / thay template
+    SaveTemplate {
+        template_id: String,
+        template: Template,
+    },
+    RemoveTemplate {
+        template_id: String,
+    },
+    // Owner tạo series và mở poll đầu tiên, funds gửi kèm là quỹ trả bounty
+    CreateSeries {
+        series_id: String,
+        template_id: String,
+        params: BTreeMap<String, String>,
+        period: u64,
+        bounty: Option<Coin>,
+    },
+    // Ai cũng gọi được khi hết period: finalize poll hiện tại, mở poll tiếp theo, nhận bounty
+    AdvanceSeries {
+        series_id: String,
+    },
+    // Nạp thêm quỹ trả bounty (funds cùng denom với bounty), quỹ còn lại thuộc về funder của series
+    FundSeries {
+        series_id: String,
+    },
+    // Owner dừng series: đóng poll đang mở, quỹ còn lại trả cho funder
+    StopSeries {
+        series_id: String,
+    },
+    // Creator sửa poll khi chưa có ai vote / commit, field None -> giữ nguyên
+    UpdatePoll {
+        poll_id: String,
+        question: Option<String>,
+        options: Option<Vec<String>>,
+        metadata: Option<PollMetadata>,
+        option_metadata: Option<Vec<OptionMetadata>>,
     },
     Vote {
         poll_id: String,
-        vote: String,
+        vote: VoteOption,
+    },
+    // Commit phase: commitment = sha256(option || salt || voter)
+    CommitVote {
+        poll_id: String,
+        commitment: Binary,
+    },
+    // Rút phiếu, chỉ với poll có VoteChangePolicy::Retractable
+    RetractVote {
+        poll_id: String,
+    },
+    // Reveal phase: gửi plaintext để contract kiểm tra với commitment đã lưu
+    RevealVote {
+        poll_id: String,
+        vote: VoteOption,
+        salt: String,
+    },
+    // Uỷ quyền phiếu cho `to`, poll_id = None -> áp dụng cho mọi poll
+    Delegate {
+        to: String,
+        poll_id: Option<String>,
+    },
+    Undelegate {
+        poll_id: Option<String>,
+    },
+    // Relayer gửi các ballot đã được voter ký off-chain (không cần gas của voter)
+    SubmitSignedVotes {
+        votes: Vec<SignedVote>,
+    },
+    // Creator hoặc admin kết thúc poll, trả lại tiền cọc cho creator
+    FinalizePoll {
+        poll_id: String,
+    },
+    // Moderator huỷ poll spam, tiền cọc chuyển về community pool
+    CancelPoll {
+        poll_id: String,
+    },
+    // Moderator ẩn poll vi phạm
+    HidePoll {
+        poll_id: String,
+    },
+    UnhidePoll {
+        poll_id: String,
+    },
+    // Báo cáo poll vi phạm, đủ report_threshold thì poll tự bị ẩn
+    ReportPoll {
+        poll_id: String,
+        reason: String,
+    },
+    // Creator hoặc Owner xoá poll đã kết thúc, chỉ giữ lại kết quả gọn
+    DeletePoll {
+        poll_id: String,
+    },
+    // Xoá dần ballot (và các record khác) của poll đã bị xoá, tối đa `limit` record mỗi lần
+    PruneBallots {
+        poll_id: String,
+        limit: Option<u32>,
     },
+    // Chốt tiếp weight của quỹ thưởng theo lô khi poll quá đông để chốt hết lúc finalize
+    SettleRewards {
+        poll_id: String,
+        limit: Option<u32>,
+    },
+    // Voter nhận phần thưởng sau khi poll được finalize
+    ClaimReward {
+        poll_id: String,
+    },
+    // Resolver báo kết quả thật của prediction market
+    ResolvePoll {
+        poll_id: String,
+        outcome: String,
+    },
+    // Resolver hoặc admin huỷ market, stake được hoàn lại
+    VoidPoll {
+        poll_id: String,
+    },
+    // Nhận tiền thắng (hoặc hoàn stake nếu market bị huỷ)
+    ClaimPayout {
+        poll_id: String,
+    },
+    // Admin đổi config, poll_deposit với amount = 0 -> bỏ đặt cọc
+    UpdateConfig {
+        admin: Option<String>,
+        poll_deposit: Option<Coin>,
+        community_pool: Option<String>,
+        market_fee: Option<Decimal>,
+        restricted_creation: Option<bool>,
+        // 0 -> tắt tự ẩn
+        report_threshold: Option<u32>,
+        // danh sách category hợp lệ, rỗng -> category tự do
+        categories: Option<Vec<String>>,
+    },
+    // Admin tạm dừng khẩn cấp, scopes = None -> dừng tất cả
+    Pause {
+        scopes: Option<Vec<PauseScope>>,
+        until_height: Option<u64>,
+    },
+    Unpause {},
+    // Owner cấp / thu hồi role
+    GrantRole {
+        address: String,
+        role: Role,
+    },
+    RevokeRole {
+        address: String,
+        role: Role,
+    },
+    // ElectorateManager thêm / bỏ voter khỏi electorate
+    UpdateElectorate {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+}
+
+// Chọn option theo index (rẻ hơn, không phải gửi cả label) hoặc theo label
+#[cw_serde]
+pub enum VoteOption {
+    Index(u32),
+    Label(String),
+}
+
+#[cw_serde]
+pub struct MarketParams {
+    // address được quyền báo kết quả
+    pub resolver: String,
+    // denom token dùng để stake
+    pub denom: String,
+    // từ block này không nhận stake nữa, resolver chỉ báo kết quả từ block này
+    pub close_height: u64,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-#[serde(rename_all = "snake_case")]
+// Ballot được ký bằng secp256k1 key của voter trên sign doc ADR-036
+// (xem helpers::signed_vote_sign_doc)
+#[cw_serde]
+pub struct SignedVote {
+    pub poll_id: String,
+    pub vote: String,
+    pub nonce: u64,
+    // compressed pubkey (33 bytes), address của voter được suy ra từ đây
+    pub pubkey: Binary,
+    // chữ ký 64 bytes (r || s)
+    pub signature: Binary,
+}
 
 // Dữ liệu khi truy vấn trạng thái -> view (các thông số public)
+#[cw_serde]
+#[derive(QueryResponses)]
 pub enum QueryMsg {
     // muốn thực hiện query chỉ xem
     // cần trả dữ liệu ở dạng binary
-    AllPoll {},
+    // Không gồm poll bị ẩn trừ khi include_hidden = true
+    #[returns(AllPollsResponse)]
+    AllPoll { include_hidden: Option<bool> },
+    #[returns(PollResponse)]
     Poll { poll_id: String },
+    #[returns(VoteResponse)]
     Vote { poll_id: String, address: String },
+    #[returns(CommitmentsResponse)]
+    Commitments { poll_id: String },
+    // Số phiếu đã tính cả phiếu được uỷ quyền
+    #[returns(TallyResponse)]
+    Tally { poll_id: String },
+    // Số phiếu, phần trăm, người thắng và trạng thái poll tại block hiện tại
+    #[returns(ResultsResponse)]
+    Results { poll_id: String },
+    // Tally trực tiếp (không tính delegation) lúc kết thúc block `height`
+    // kèm số voter sau mỗi block có thay đổi tới height đó, phân trang theo height
+    #[returns(ResultsAtResponse)]
+    ResultsAt {
+        poll_id: String,
+        height: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(DelegationResponse)]
+    Delegation {
+        address: String,
+        poll_id: Option<String>,
+    },
+    // nonce mà signed vote tiếp theo của address phải dùng
+    #[returns(NonceResponse)]
+    Nonce { address: String },
+    #[returns(Config)]
+    Config {},
+    // Bộ đếm toàn contract, trả về state::Stats
+    #[returns(Stats)]
+    Stats {},
+    // Poll đã tạo, poll đã vote, tiền chờ claim và delegation của một address.
+    // Mỗi danh sách có cursor riêng và chỉ xét tối đa `limit` phần tử sau cursor đó:
+    // created_after -> created / deposits, voted_after -> voted / rewards / payouts
+    // (poll_id lớn nhất của trang trước), delegations_after -> scope của delegations
+    #[returns(UserOverviewResponse)]
+    UserOverview {
+        address: String,
+        created_after: Option<String>,
+        voted_after: Option<String>,
+        delegations_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(DepositResponse)]
+    Deposit { poll_id: String },
+    // Danh sách tiền cọc đang giữ
+    #[returns(DepositsResponse)]
+    Deposits {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(RewardResponse)]
+    Reward { poll_id: String, address: String },
+    #[returns(MarketResponse)]
+    Market { poll_id: String },
+    #[returns(PayoutResponse)]
+    Payout { poll_id: String, address: String },
+    // Các poll có tag, theo thứ tự poll_id
+    #[returns(PollsResponse)]
+    PollsByTag {
+        tag: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+        include_hidden: Option<bool>,
+    },
+    // Tìm poll có câu hỏi (đã chuẩn hoá) bắt đầu bằng `prefix`
+    #[returns(PollsResponse)]
+    SearchPolls {
+        prefix: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+        include_hidden: Option<bool>,
+    },
+    #[returns(TemplateResponse)]
+    Template { template_id: String },
+    #[returns(TemplatesResponse)]
+    Templates {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(SeriesResponse)]
+    Series { series_id: String },
+    // Các poll đã mở của series theo thứ tự instance
+    #[returns(SeriesHistoryResponse)]
+    SeriesHistory {
+        series_id: String,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    },
+    // Kết quả gọn của poll đã bị xoá
+    #[returns(PollResultResponse)]
+    PollResult { poll_id: String },
+    // Lịch sử sửa poll theo thứ tự revision
+    #[returns(PollHistoryResponse)]
+    PollHistory {
+        poll_id: String,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    },
+    // Các report của một poll
+    #[returns(ReportsResponse)]
+    Reports {
+        poll_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(RolesResponse)]
+    Roles { address: String },
+    // Các address có role, theo thứ tự address
+    #[returns(RoleMembersResponse)]
+    RoleMembers {
+        role: Role,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(ElectorateResponse)]
+    Electorate {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-#[serde(rename_all = "snake_case")]
-
 // Dữ liệu khi nâng cấp contract
-pub enum MigrateMsg {}
+#[cw_serde]
+pub struct MigrateMsg {}
@@ -5,35 +5,482 @@ use serde::{Deserialize, Serialize};
 // Serialize : Chuyển đổi struct/enum -> JSON
 // Deserialize: chuyển đổi JSON -> struct/enum
 
-use cosmwasm_std::Addr; // làm việc với Cosmos address
-                        //Addr thực chất là wrapped String????
+use cosmwasm_std::{Addr, Binary, BlockInfo, Coin, Decimal, Timestamp, Uint128}; // làm việc với Cosmos address
+                                                                                //Addr thực chất là wrapped String????
 
-use cw_storage_plus::{Item, Map}; // Lưu trữ giá trị trên chain
-                                  // Moi Item la mot bien trangj thai, moi Iteam chi luu 1 bien
-                                  // tuong tu moi Map chi luu mot map
+use cw_storage_plus::{Item, Map, SnapshotMap, Strategy};
+use std::collections::BTreeMap; // Lưu trữ giá trị trên chain
+                                // Moi Item la mot bien trangj thai, moi Iteam chi luu 1 bien
+                                // tuong tu moi Map chi luu mot map
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)] // marco để impl trait cho struct
 pub struct Config {
     // cấu trúc định nghĩa các biến state
     pub admin: Addr, // Admin address
+    // tiền đặt cọc khi tạo poll, None -> tạo poll miễn phí
+    #[serde(default)]
+    pub poll_deposit: Option<Coin>,
+    // nơi nhận tiền cọc của poll bị admin huỷ vì spam
+    pub community_pool: Addr,
+    // phí giao thức lấy từ phần stake thua của prediction market, gửi cho admin
+    #[serde(default)]
+    pub market_fee: Decimal,
+    // Some -> contract đang tạm dừng một số thao tác
+    #[serde(default)]
+    pub paused: Option<PauseState>,
+    // true -> chỉ address có role PollCreator mới được tạo poll
+    #[serde(default)]
+    pub restricted_creation: bool,
+    // đủ số report thì poll tự bị ẩn, None -> không tự ẩn
+    #[serde(default)]
+    pub report_threshold: Option<u32>,
+    // category hợp lệ cho poll, rỗng -> category tự do
+    #[serde(default)]
+    pub categories: Vec<String>,
+}
+
+// Các quyền có thể cấp cho một address, admin trong Config luôn có mọi quyền
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    // quyền như admin: đổi config, pause, cấp / thu hồi role
+    Owner,
+    // được tạo poll khi bật restricted_creation
+    PollCreator,
+    // huỷ / ẩn poll vi phạm
+    Moderator,
+    // quản lý danh sách electorate
+    ElectorateManager,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PauseState {
+    pub scopes: Vec<PauseScope>,
+    // tự hết pause từ block này (None -> tới khi admin Unpause)
+    pub until_height: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseScope {
+    PollCreation,
+    // vote, commit/reveal, signed vote, delegation
+    Voting,
+    // nhận thưởng / payout
+    Claims,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Poll {
     //Lưu thông tin Poll
+    pub creator: Addr,
+    pub question: String,
+    // các option nằm trong OPTIONS, số phiếu trong TALLIES, key (poll_id, index)
+    #[serde(default)]
+    pub option_count: u32,
+    #[serde(default)]
+    pub voting_mode: VotingMode,
+    #[serde(default)]
+    pub status: PollStatus,
+    // ai được vote trong poll này
+    #[serde(default)]
+    pub eligibility: Eligibility,
+    // moderator ẩn poll vi phạm
+    #[serde(default)]
+    pub hidden: bool,
+    // số address đã report poll
+    #[serde(default)]
+    pub reports: u32,
+    #[serde(default)]
+    pub metadata: PollMetadata,
+    // tăng mỗi lần creator sửa poll, 0 = bản lúc tạo
+    #[serde(default)]
+    pub revision: u32,
+    // poll công khai ngừng nhận phiếu sau block này, None -> tới khi finalize
+    #[serde(default)]
+    pub end_height: Option<u64>,
+    // voter có được đổi / rút phiếu hay không
+    #[serde(default)]
+    pub vote_change: VoteChangePolicy,
+    // block poll bị finalize / huỷ
+    #[serde(default)]
+    pub closed_height: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteChangePolicy {
+    // chỉ được vote một lần
+    Immutable,
+    // đổi phiếu bao nhiêu lần cũng được (cách cũ)
+    #[default]
+    Changeable,
+    // đổi phiếu được tới hết deadline, sau đó phiếu bị khoá
+    ChangeableUntil(Deadline),
+    // đổi phiếu được và rút hẳn phiếu bằng RetractVote
+    Retractable,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Deadline {
+    Height(u64),
+    Time(Timestamp),
+}
+
+impl Deadline {
+    pub fn is_passed(&self, block: &BlockInfo) -> bool {
+        match self {
+            Deadline::Height(height) => block.height >= *height,
+            Deadline::Time(time) => block.time >= *time,
+        }
+    }
+}
+
+// Mẫu poll cho các khảo sát lặp lại, "{key}" trong question / options
+// được thay bằng params khi tạo poll
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Template {
+    pub question: String,
+    pub options: Vec<String>,
+    pub voting_mode: TemplateVotingMode,
+    // số block poll công khai mở để vote, None -> tới khi finalize
+    pub duration: Option<u64>,
+    pub eligibility: Eligibility,
+}
+
+// Chuỗi poll định kỳ tạo từ một template, mỗi `period` block ai cũng có thể
+// gọi AdvanceSeries để đóng poll hiện tại, mở poll tiếp theo và nhận bounty
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Series {
+    pub template_id: String,
+    // params cho template, thêm "instance" = số thứ tự của poll
+    pub params: BTreeMap<String, String>,
+    pub period: u64,
+    // số tiền trả cho người gọi AdvanceSeries
+    pub bounty: Option<Coin>,
+    // quỹ còn lại để trả bounty (cùng denom với bounty)
+    pub fund: Uint128,
+    // người tạo series, nhận lại phần quỹ còn lại khi series dừng
+    pub funder: Addr,
+    // số poll đã mở
+    pub instance: u32,
+    pub current: Option<String>,
+    // được advance từ block này
+    pub next_height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SeriesInstance {
+    pub poll_id: String,
+    pub opened_height: u64,
+    pub closed_height: Option<u64>,
+}
+
+// Như VotingMode nhưng các phase tính bằng số block kể từ lúc tạo poll
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateVotingMode {
+    Public,
+    CommitReveal {
+        commit_blocks: u64,
+        reveal_blocks: u64,
+    },
+}
+
+// Nội dung của poll tại một revision, để voter kiểm tra được mình đã vote cho bản nào
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PollRevision {
+    pub revision: u32,
+    // block lưu revision này
+    pub height: u64,
+    pub question: String,
+    pub options: Vec<String>,
+    pub metadata: PollMetadata,
+    pub option_metadata: Vec<OptionMetadata>,
+}
+
+// Thông tin thêm để hiển thị poll, không ảnh hưởng tới việc vote
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct PollMetadata {
+    pub description: Option<String>,
+    pub external_url: Option<String>,
+    pub image_uri: Option<String>,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct OptionMetadata {
+    pub description: Option<String>,
+    pub uri: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OptionInfo {
+    pub label: String,
+    pub description: Option<String>,
+    pub uri: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Eligibility {
+    #[default]
+    Anyone,
+    // chỉ address nằm trong ELECTORATE
+    Electorate,
+}
+
+// Kết quả gọn giữ lại sau khi poll bị xoá, options chỉ tính phiếu trực tiếp
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PollResult {
     pub creator: Addr,
     pub question: String,
     pub options: Vec<(String, u64)>,
+    pub status: PollStatus,
+    pub deleted_height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PollStatus {
+    #[default]
+    Open,
+    // kết thúc bình thường, tiền cọc trả lại creator
+    Finalized,
+    // admin huỷ (spam), tiền cọc chuyển về community pool
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum VotingMode {
+    // Ballot công khai ngay khi vote (cách cũ)
+    #[default]
+    Public,
+    // Commit-reveal: tới commit_end_height chỉ gửi hash sha256(option || salt || voter),
+    // sau đó tới reveal_end_height mới gửi plaintext để contract kiểm tra và cộng phiếu
+    CommitReveal {
+        commit_end_height: u64,
+        reveal_end_height: u64,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Ballot {
-    // Lưu thông tin vote của user
-    pub option: String,
+    // Lưu thông tin vote của user: index của option đã chọn
+    pub option: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Deposit {
+    // tiền cọc đang giữ cho một poll
+    pub depositor: Addr,
+    pub amount: Coin,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardSplit {
+    // chia đều cho mọi voter
+    Equal,
+    // chia theo weight = 1 + số phiếu được uỷ quyền cho voter
+    Weighted,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RewardPool {
+    // quỹ thưởng creator gửi kèm khi tạo poll
+    pub funds: Coin,
+    pub split: RewardSplit,
+    // tổng weight của các voter, chốt theo lô từ lúc finalize
+    pub total_weight: u64,
+    // voter cuối cùng đã được chốt weight
+    #[serde(default)]
+    pub settle_cursor: Option<Addr>,
+    // đã chốt xong weight của mọi voter, lúc này mới claim được
+    #[serde(default)]
+    pub settled: bool,
+    // tổng weight đã claim và số tiền đã trả, voter cuối claim thì phần lẻ trả lại creator
+    #[serde(default)]
+    pub claimed_weight: u64,
+    #[serde(default)]
+    pub paid: Uint128,
+}
+
+// Prediction market gắn với một poll: voter stake token vào option,
+// resolver báo kết quả thật, bên thắng chia phần stake của bên thua (parimutuel)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Market {
+    pub resolver: Addr,
+    pub denom: String,
+    // tổng stake theo từng option, theo index của option
+    pub stakes: Vec<Uint128>,
+    pub status: MarketStatus,
+    // từ block này không nhận stake nữa và mới được resolve
+    pub close_height: u64,
+    // tổng stake của những người đã claim và số tiền đã trả,
+    // người thắng cuối cùng claim thì phần lẻ gửi cho admin cùng với phí
+    #[serde(default)]
+    pub claimed_stake: Uint128,
+    #[serde(default)]
+    pub paid: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketStatus {
+    Open,
+    Resolved {
+        // index của option thắng
+        winner: u32,
+        // tổng stake của bên thắng
+        winning_stake: Uint128,
+        // stake của bên thua sau khi trừ phí, chia cho bên thắng
+        pot: Uint128,
+    },
+    // huỷ market, mọi người được hoàn stake
+    Voided,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Commitment {
+    // hash đã commit, chưa tiết lộ lựa chọn
+    pub hash: Binary,
+    pub revealed: bool,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config"); //lưu các item(các biến đơn)
 
+// Bộ đếm toàn contract, cập nhật dần khi tạo poll / vote để query Stats không phải duyệt POLLS
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct Stats {
+    pub total_polls: u64,
+    pub open_polls: u64,
+    // số ballot đang được tính, mỗi (voter, poll) một ballot
+    pub total_ballots: u64,
+    pub unique_voters: u64,
+    // (poll_id, số voter) giảm dần, tối đa TOP_POLLS_LEN poll
+    pub top_polls: Vec<(String, u64)>,
+}
+
+pub const TOP_POLLS_LEN: usize = 10;
+
+pub const STATS: Item<Stats> = Item::new("stats");
+
+// address đã từng vote ít nhất một lần
+pub const VOTERS: Map<Addr, ()> = Map::new("voters");
+
 pub const POLLS: Map<String, Poll> = Map::new("polls");
 
 pub const BALLOTS: Map<(Addr, String), Ballot> = Map::new("ballots");
+
+// Tách khỏi POLLS để mỗi lần vote không phải đọc / ghi lại cả poll
+// (poll_id, index) -> option
+pub const OPTIONS: Map<(String, u32), OptionInfo> = Map::new("options");
+
+// (poll_id, index) -> số phiếu trực tiếp của option (chưa tính delegation),
+// giữ changelog theo từng block để query lại tally tại một height trong quá khứ
+pub const TALLIES: SnapshotMap<(String, u32), Uint128> = SnapshotMap::new(
+    "tallies",
+    "tallies__checkpoints",
+    "tallies__changelog",
+    Strategy::EveryBlock,
+);
+
+// (poll_id, height) -> số voter của poll sau block đó, chỉ ghi ở block có thay đổi
+pub const TURNOUT: Map<(String, u64), u64> = Map::new("turnout");
+
+// poll_id -> tiền cọc chưa được trả / slash
+pub const DEPOSITS: Map<String, Deposit> = Map::new("deposits");
+
+// index (creator, poll_id) các poll một address đã tạo, poll đã xoá bị bỏ khỏi index
+pub const CREATED_POLLS: Map<(Addr, String), ()> = Map::new("created_polls");
+
+// index (poll_id, voter) của những người đã có ballot trong poll
+pub const POLL_VOTERS: Map<(String, Addr), ()> = Map::new("poll_voters");
+
+pub const REWARD_POOLS: Map<String, RewardPool> = Map::new("reward_pools");
+
+// weight của từng voter khi poll được finalize
+pub const VOTER_WEIGHTS: Map<(String, Addr), u64> = Map::new("voter_weights");
+
+pub const REWARD_CLAIMED: Map<(String, Addr), bool> = Map::new("reward_claimed");
+
+// (poll_id, voter) -> commitment, key theo poll trước để liệt kê được các commitment của một poll
+pub const COMMITMENTS: Map<(String, Addr), Commitment> = Map::new("commitments");
+
+// scope của delegation: GLOBAL_SCOPE áp dụng cho mọi poll, còn lại là poll_id
+pub const GLOBAL_SCOPE: &str = "";
+
+// (delegator, scope) -> delegate
+pub const DELEGATIONS: Map<(Addr, String), Addr> = Map::new("delegations");
+
+// index ngược (delegate, scope, delegator) để query ai đang uỷ quyền cho một address
+pub const DELEGATORS: Map<(Addr, String, Addr), ()> = Map::new("delegators");
+
+// index (poll_id, delegator) của các delegation riêng cho một poll, để prune theo poll
+pub const POLL_DELEGATIONS: Map<(String, Addr), ()> = Map::new("poll_delegations");
+
+// nonce tiếp theo của mỗi voter cho signed vote, chống replay
+pub const NONCES: Map<Addr, u64> = Map::new("nonces");
+
+pub const MARKETS: Map<String, Market> = Map::new("markets");
+
+// (poll_id, voter) -> số token voter đã stake
+pub const STAKES: Map<(String, Addr), Uint128> = Map::new("stakes");
+
+pub const PAYOUT_CLAIMED: Map<(String, Addr), bool> = Map::new("payout_claimed");
+
+pub const TEMPLATES: Map<String, Template> = Map::new("templates");
+
+pub const SERIES: Map<String, Series> = Map::new("series");
+
+// (series_id, instance) -> poll đã mở cho instance đó
+pub const SERIES_HISTORY: Map<(String, u32), SeriesInstance> = Map::new("series_history");
+
+// poll_id -> kết quả cuối của poll đã bị xoá
+pub const POLL_RESULTS: Map<String, PollResult> = Map::new("poll_results");
+
+// (poll_id, revision) -> nội dung poll tại revision đó
+pub const POLL_REVISIONS: Map<(String, u32), PollRevision> = Map::new("poll_revisions");
+
+// (tag đã chuẩn hoá, poll_id)
+pub const TAG_INDEX: Map<(String, String), ()> = Map::new("tag_index");
+
+// "<câu hỏi đã chuẩn hoá>:<poll_id>" -> poll_id, dùng cho tìm kiếm theo prefix.
+// Key là một string để range giữ đúng thứ tự từ điển của câu hỏi
+pub const QUESTION_INDEX: Map<String, String> = Map::new("question_index");
+
+// (poll_id, reporter) -> lý do report, mỗi address report một poll một lần
+pub const REPORTS: Map<(String, Addr), String> = Map::new("reports");
+
+// address -> các role được cấp
+pub const ROLES: Map<Addr, Vec<Role>> = Map::new("roles");
+
+// danh sách voter cho poll có Eligibility::Electorate, do ElectorateManager quản lý
+pub const ELECTORATE: Map<Addr, ()> = Map::new("electorate");
+
+// Layout của bản 0.1.x, chỉ dùng khi migrate: config chỉ có admin, options và số phiếu
+// nằm ngay trong poll, ballot lưu label của option. Cùng namespace với CONFIG / POLLS / BALLOTS
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyConfig {
+    pub admin: Addr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyPoll {
+    pub creator: Addr,
+    pub question: String,
+    pub options: Vec<(String, u64)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyBallot {
+    pub option: String,
+}
+
+pub const LEGACY_CONFIG: Item<LegacyConfig> = Item::new("config");
+pub const LEGACY_POLLS: Map<String, LegacyPoll> = Map::new("polls");
+pub const LEGACY_BALLOTS: Map<(Addr, String), LegacyBallot> = Map::new("ballots");